@@ -0,0 +1,70 @@
+use std::path::PathBuf;
+
+use directories::ProjectDirs;
+
+const HISTORY_LEN: usize = 100;
+
+/// Command lines entered so far, shared by every buffer and kept across
+/// sessions in the data directory.
+#[derive(Default)]
+pub struct CmdHistory {
+    entries: Vec<String>,
+    file: Option<PathBuf>,
+}
+
+impl CmdHistory {
+    pub fn load() -> Self {
+        let file = ProjectDirs::from("com", "Taice", "Sucocku")
+            .map(|dirs| dirs.data_dir().join("history"));
+        let entries = file
+            .as_ref()
+            .and_then(|file| std::fs::read_to_string(file).ok())
+            .map(|content| content.lines().map(String::from).collect())
+            .unwrap_or_default();
+        Self { entries, file }
+    }
+
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    pub fn push(&mut self, line: &str) {
+        let line = line.trim();
+        if line.is_empty() {
+            return;
+        }
+        self.entries.retain(|x| x != line);
+        self.entries.push(line.to_string());
+        if self.entries.len() > HISTORY_LEN {
+            self.entries.drain(..self.entries.len() - HISTORY_LEN);
+        }
+        self.save();
+    }
+
+    fn save(&self) {
+        let Some(file) = &self.file else {
+            return;
+        };
+        // losing the history isn't worth bothering the user about
+        if let Some(dir) = file.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        let _ = std::fs::write(file, self.entries.join("\n"));
+    }
+}
+
+#[test]
+fn history_works() {
+    let mut history = CmdHistory::default();
+    history.push("set wrap=true");
+    history.push("  ");
+    history.push("undo");
+    history.push("set wrap=true");
+    assert_eq!(history.entries(), ["undo", "set wrap=true"]);
+
+    for i in 0..HISTORY_LEN {
+        history.push(&i.to_string());
+    }
+    assert_eq!(history.entries().len(), HISTORY_LEN);
+    assert_eq!(history.entries()[0], "0");
+}
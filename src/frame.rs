@@ -5,16 +5,26 @@ mod window;
 use macroquad::prelude::*;
 use split::Split;
 use std::cell::RefCell;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use std::time::SystemTime;
 
 use window::Window;
 
 use window::buffer::Buffer;
 
+use crate::cmd_history::CmdHistory;
 use crate::draw_rect_outlines;
 use crate::frame::split::SplitDirection;
 use crate::frame::tab::Tab;
-use crate::settings::{FONT_SCALE, Settings};
+use crate::input::{Key, poll_key};
+use crate::messages::Messages;
+use crate::registers::{Register, Registers};
+use crate::settings::{
+    FONT_SCALE, Settings, check_keymaps,
+    config::rc_file,
+    options::{self, Scope},
+};
 use crate::sudoku::Sudoku;
 
 enum Mode {
@@ -22,6 +32,43 @@ enum Mode {
     Normal,
 }
 
+/// Short names `Frame::process_cmd` takes besides `COMMANDS`.
+pub const ALIASES: &[&str] = &[
+    "tabe", "tabc", "tabn", "tabp", "sp", "vs", "clo", "on", "winc", "enew", "bn", "bp", "bd", "b",
+    "buffers", "redr", "setl", "so",
+];
+
+/// The modes frame keymaps are bound in, one key each.
+pub const MODES: &[&str] = &["frame", "buffer"];
+
+/// Every command `Frame::process_cmd` knows, for completion.
+pub const COMMANDS: &[&str] = &[
+    "tabnew",
+    "tabclose",
+    "tabnext",
+    "tabprevious",
+    "split",
+    "vsplit",
+    "close",
+    "only",
+    "wincmd",
+    "bnew",
+    "bnext",
+    "bprevious",
+    "bdelete",
+    "buffer",
+    "ls",
+    "buffermode",
+    "redraw",
+    "setlocal",
+    "source",
+];
+
+/// Seconds between checks of the config file for changes.
+const CONFIG_POLL_INTERVAL: f64 = 1.0;
+/// How deep sourced files can source other files.
+const MAX_SOURCE_DEPTH: u8 = 16;
+
 pub struct Frame {
     tabs: Vec<Tab>,
     curr_tab: usize,
@@ -32,11 +79,24 @@ pub struct Frame {
 
     tabn: usize,
     settings: Rc<RefCell<Settings>>,
+    registers: Rc<RefCell<Registers>>,
+    cmd_history: Rc<RefCell<CmdHistory>>,
+    messages: Rc<RefCell<Messages>>,
     size: (f32, f32),
+
+    /// `--set` overrides, applied again on every reload.
+    overrides: Vec<String>,
+    config_mtimes: Vec<Option<SystemTime>>,
+    last_config_check: f64,
+    source_depth: u8,
 }
 
 impl Frame {
-    pub fn new(settings: Settings) -> Self {
+    /// `problems` are the ones found loading the config, kept for `:messages`.
+    pub fn new(settings: Settings, problems: Vec<String>, overrides: Vec<String>) -> Self {
+        let registers = Rc::new(RefCell::new(Registers::new(settings.registers.clone())));
+        let cmd_history = Rc::new(RefCell::new(CmdHistory::load()));
+        let messages = Rc::new(RefCell::new(Messages::default()));
         let settings = Rc::new(RefCell::new(settings));
         let mut ret = Self {
             mode: Mode::Normal,
@@ -49,11 +109,36 @@ impl Frame {
             }],
             tabn: 1,
             curr_tab: 0,
-            buffers: vec![Buffer::new(Rc::clone(&settings))],
-            settings: settings,
+            buffers: vec![Buffer::new(
+                Rc::clone(&settings),
+                Rc::clone(&registers),
+                Rc::clone(&cmd_history),
+                Rc::clone(&messages),
+            )],
+            settings,
+            registers,
+            cmd_history,
+            messages,
             size: (0.0, 0.0),
+            overrides,
+            config_mtimes: vec![],
+            last_config_check: 0.,
+            source_depth: 0,
         };
+        ret.config_mtimes = ret.config_mtimes();
         ret.update();
+        let timeout = ret.settings.borrow().opts.timeoutlen > 0;
+        let keymap_problems = check_keymaps(&ret.settings.borrow().keymaps, timeout).len();
+        if keymap_problems > 0 {
+            ret.log(format!("{keymap_problems} keymap problems, see :checkmaps"));
+        }
+        if !problems.is_empty() {
+            ret.report(format!("{} config problems", problems.len()), problems);
+        }
+        let rc = rc_file();
+        if rc.exists() {
+            ret.source(&rc.to_string_lossy());
+        }
         ret
     }
 
@@ -75,7 +160,7 @@ impl Frame {
                 self.settings.borrow().colors.window_gaps,
             );
         }
-        if let Split::Window(win) = self.tabs[self.curr_tab][self.tabs[self.curr_tab].selected] {
+        if let Split::Window(win) = &self.tabs[self.curr_tab][self.tabs[self.curr_tab].selected] {
             // draw selcete window outline
             let mut dimensions = win.dimensions;
             dimensions.x -= half;
@@ -116,7 +201,11 @@ impl Frame {
             self.size = (width, height);
             self.resize();
         }
+        self.watch_config();
         self.handle_input();
+        let curr = self.curr_buffer();
+        self.buffers[curr].data.tick();
+        self.run_frame_cmds(curr);
     }
 
     fn new_tab(&mut self) {
@@ -129,7 +218,12 @@ impl Frame {
         });
         self.curr_tab = self.tabs.len() - 1;
         self.buffers.push(Buffer {
-            data: Sudoku::new(Rc::clone(&self.settings)),
+            data: Sudoku::new(
+                Rc::clone(&self.settings),
+                Rc::clone(&self.registers),
+                Rc::clone(&self.cmd_history),
+                Rc::clone(&self.messages),
+            ),
         });
         self.resize();
     }
@@ -151,12 +245,26 @@ impl Frame {
         self.resize();
     }
 
+    fn go_to_tab(&mut self, n: usize) {
+        if (1..=self.tabs.len()).contains(&n) {
+            self.curr_tab = n - 1;
+            self.resize();
+        } else {
+            self.log(format!("No tab {n}"));
+        }
+    }
+
     fn new_buffer(&mut self) {
         let idx = self.tabs[self.curr_tab].selected;
         if let Split::Window(win) = &mut self.tabs[self.curr_tab][idx] {
             win.buffer_index = self.buffers.len();
             self.buffers.push(Buffer {
-                data: Sudoku::new(Rc::clone(&self.settings)),
+                data: Sudoku::new(
+                    Rc::clone(&self.settings),
+                    Rc::clone(&self.registers),
+                    Rc::clone(&self.cmd_history),
+                    Rc::clone(&self.messages),
+                ),
             });
         }
     }
@@ -171,6 +279,11 @@ impl Frame {
         self.resize();
     }
 
+    fn only(&mut self) {
+        self.tabs[self.curr_tab].only();
+        self.resize();
+    }
+
     fn buffer_mode(&mut self) {
         self.mode = Mode::Buffer;
     }
@@ -179,101 +292,316 @@ impl Frame {
         self.tabs[self.curr_tab].switch_buffer(n, self.buffers.len());
     }
 
-    fn kill_buffer(&mut self) {
-        let mut len = self.buffers.len();
-        if len > 1 {
-            let win_idx = self.tabs[self.curr_tab].selected;
-            if let Split::Window(win) = self.tabs[self.curr_tab][win_idx] {
-                self.buffers.remove(win.buffer_index);
-            }
-            len -= 1;
-            for win in self.tabs[self.curr_tab].inner.iter_mut() {
-                if win.buffer_index >= len {
-                    win.buffer_index = len - 1;
+    fn kill_buffer(&mut self, idx: usize) {
+        let len = self.buffers.len();
+        if len > 1 && idx < len {
+            self.buffers.remove(idx);
+            for tab in &mut self.tabs {
+                for win in tab.inner.iter_mut() {
+                    if win.buffer_index > idx || win.buffer_index == len - 1 {
+                        win.buffer_index -= 1;
+                    }
                 }
             }
         }
     }
 
-    fn handle_input(&mut self) {
-        match self.mode {
-            Mode::Buffer => self.handle_input_buffer(),
-            Mode::Normal => self.handle_input_normal(),
+    fn go_to_buffer(&mut self, n: usize) {
+        if !(1..=self.buffers.len()).contains(&n) {
+            self.log(format!("No buffer {n}"));
+            return;
+        }
+        let idx = self.tabs[self.curr_tab].selected;
+        if let Split::Window(win) = &mut self.tabs[self.curr_tab][idx] {
+            win.buffer_index = n - 1;
         }
     }
 
-    fn handle_input_buffer(&mut self) {
-        if let Some(k) = get_last_key_pressed() {
-            match k {
-                KeyCode::Tab => {
-                    self.switch_buffer(1);
-                    return;
+    fn list_buffers(&mut self) {
+        let curr = self.curr_buffer();
+        let list = (0..self.buffers.len())
+            .map(|i| {
+                if i == curr {
+                    format!("[{}]", i + 1)
+                } else {
+                    (i + 1).to_string()
                 }
-                KeyCode::Escape => {
-                    self.mode = Mode::Normal;
-                }
-                _ => (),
-            }
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        self.log(format!("buffers: {list}"));
+    }
+
+    fn config_mtimes(&self) -> Vec<Option<SystemTime>> {
+        self.settings
+            .borrow()
+            .config_files
+            .iter()
+            .map(|x| std::fs::metadata(x).and_then(|x| x.modified()).ok())
+            .collect()
+    }
+
+    /// Reloads the config once one of its files changes on disk, checking
+    /// every so often.
+    fn watch_config(&mut self) {
+        let now = get_time();
+        if now - self.last_config_check < CONFIG_POLL_INTERVAL {
+            return;
         }
-        if let Some(c) = get_char_pressed() {
-            self.mode = Mode::Normal;
-            match c {
-                'l' => self.switch_buffer(1),
-                'h' => self.switch_buffer(-1),
-                'n' => self.new_buffer(),
-                'k' => self.kill_buffer(),
-                _ => self.mode = Mode::Buffer,
+        self.last_config_check = now;
+        if self.config_mtimes() != self.config_mtimes {
+            self.reload_config();
+        }
+    }
+
+    /// Reloads every config layer, see `config::load_layers`.
+    fn reload_config(&mut self) {
+        let (settings, problems) = Settings::load(&self.overrides);
+        self.set_settings(settings, problems, "the config");
+    }
+
+    /// Reloads the config layers with a config file merged over them,
+    /// keeping the old settings if it doesn't load.
+    fn load_config(&mut self, file: &Path) {
+        match Settings::load_over(file, &self.overrides) {
+            Ok((settings, problems)) => {
+                self.set_settings(settings, problems, &file.display().to_string())
             }
+            Err(err_msg) => self.log(err_msg),
         }
     }
 
-    fn handle_input_normal(&mut self) {
-        if is_key_down(KeyCode::LeftControl) {
-            self.handle_frame_input();
+    /// Swaps in newly loaded settings, `name` saying where from.
+    fn set_settings(&mut self, settings: Settings, problems: Vec<String>, name: &str) {
+        for (name, keys) in &settings.registers {
+            let register = Register::Keys(keys.clone());
+            self.registers.borrow_mut().set(*name, register);
+        }
+        *self.settings.borrow_mut() = settings;
+        self.config_mtimes = self.config_mtimes();
+        self.resize();
+        if problems.is_empty() {
+            self.log(format!("Loaded {name}"));
         } else {
-            if let Split::Window(selected_win) =
-                &self.tabs[self.curr_tab][self.tabs[self.curr_tab].selected]
-            {
-                selected_win.update(&mut self.buffers[selected_win.buffer_index]);
+            let summary = format!("Loaded {name} with {} problems", problems.len());
+            self.report(summary, problems);
+        }
+        let timeout = self.settings.borrow().opts.timeoutlen > 0;
+        let keymap_problems = check_keymaps(&self.settings.borrow().keymaps, timeout).len();
+        if keymap_problems > 0 {
+            self.log(format!("{keymap_problems} keymap problems, see :checkmaps"));
+        }
+    }
+
+    /// Keeps `problems` for `:messages` and logs `summary` pointing there.
+    fn report(&mut self, summary: String, problems: Vec<String>) {
+        for problem in problems {
+            self.messages.borrow_mut().push(problem);
+        }
+        self.log(format!("{summary}, see :messages"));
+    }
+
+    /// `source [file]` loads a `.toml` config over the config layers, every
+    /// layer again without a file, and runs anything else as commands, one per
+    /// line. What the commands report is collected and kept for `:messages`.
+    fn source(&mut self, args: &str) {
+        if args.is_empty() {
+            self.reload_config();
+            return;
+        }
+        let file = PathBuf::from(args);
+        if file.extension().is_some_and(|x| x == "toml") {
+            self.load_config(&file);
+            return;
+        }
+        if self.source_depth >= MAX_SOURCE_DEPTH {
+            self.log("Source recursion too deep".to_string());
+            return;
+        }
+        let content = match std::fs::read_to_string(&file) {
+            Ok(x) => x,
+            Err(e) => {
+                self.log(format!("{}: {e}", file.display()));
+                return;
+            }
+        };
+        self.source_depth += 1;
+        let mut problems = vec![];
+        for (i, line) in content.lines().map(str::trim).enumerate() {
+            // `"` starts a comment, like in vim scripts
+            if line.is_empty() || line.starts_with('"') {
+                continue;
             }
+            let count = self.messages.borrow().count();
+            let curr = self.curr_buffer();
+            self.buffers[curr]
+                .data
+                .execute(line.strip_prefix(':').unwrap_or(line));
+            self.run_frame_cmds(curr);
+            for msg in self.messages.borrow_mut().take_since(count) {
+                problems.push(format!("{}:{}: {msg}", file.display(), i + 1));
+            }
+        }
+        self.source_depth -= 1;
+        if problems.is_empty() {
+            return;
         }
+        let summary = format!("{} messages from {}", problems.len(), file.display());
+        self.report(summary, problems);
     }
 
-    fn handle_frame_input(&mut self) {
-        if let Some(kc) = get_last_key_pressed() {
-            match kc {
-                KeyCode::Tab => {
-                    self.switch_tab(if is_key_down(KeyCode::LeftShift) {
-                        -1
-                    } else {
-                        1
-                    });
-                }
-                _ => (),
+    /// `setlocal` lists the selected buffer's and window's own options, and
+    /// `setlocal {args}` overrides them, see `LocalOpts::set`.
+    fn set_local(&mut self, args: &str) {
+        let curr = self.curr_buffer();
+        let selected = self.tabs[self.curr_tab].selected;
+        let Split::Window(win) = &mut self.tabs[self.curr_tab][selected] else {
+            unreachable!()
+        };
+        let buffer = &mut self.buffers[curr].data;
+        let mut msgs: Vec<_> = if args.is_empty() {
+            buffer
+                .local_opts
+                .list()
+                .chain(win.local_opts.list())
+                .collect()
+        } else {
+            vec![]
+        };
+        for arg in args.split_whitespace() {
+            let mut opts = buffer.opts();
+            win.local_opts.apply(&mut opts);
+            let res = options::parse_arg(arg).and_then(|(option, _)| match option.scope {
+                Scope::Buffer => buffer.local_opts.set(arg, &opts),
+                Scope::Window => win.local_opts.set(arg, &opts),
+                Scope::Global => Err(format!("Not a local option: {}", option.name)),
+            });
+            match res {
+                Ok(msg) => msgs.extend(msg),
+                Err(err_msg) => msgs.push(err_msg),
             }
         }
-        if let Some(ch) = get_char_pressed() {
-            match ch {
-                '\\' => self.split(SplitDirection::Vertical),
-                '-' => self.split(SplitDirection::Horizontal),
-                'h' => {
-                    self.tabs[self.curr_tab].move_left(self.settings.borrow().lines.window_gaps);
-                }
-                'l' => {
-                    self.tabs[self.curr_tab].move_right(self.settings.borrow().lines.window_gaps);
-                }
-                'k' => {
-                    self.tabs[self.curr_tab].move_up(self.settings.borrow().lines.window_gaps);
+        if !msgs.is_empty() {
+            let curr = self.curr_buffer();
+            self.buffers[curr].data.show(msgs);
+        }
+    }
+
+    fn curr_buffer(&self) -> usize {
+        match &self.tabs[self.curr_tab][self.tabs[self.curr_tab].selected] {
+            Split::Window(win) => win.buffer_index,
+            Split::Split(..) => unreachable!(),
+        }
+    }
+
+    /// Shows a message on the selected buffer's command line.
+    fn log(&mut self, msg: String) {
+        let curr = self.curr_buffer();
+        self.buffers[curr].data.cmd_log(msg);
+    }
+
+    fn process_cmd(&mut self, cmd: &str) {
+        let cmd = cmd.trim();
+        let name_start = cmd.len() - cmd.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        let count = cmd[..name_start].parse::<usize>().ok();
+        let (name, args) = cmd[name_start..]
+            .split_once(' ')
+            .map_or((&cmd[name_start..], ""), |(name, args)| (name, args.trim()));
+        // `:tabn 2` and `:2tabn` mean the same
+        let n = args.parse::<usize>().ok().or(count);
+        let gaps = self.settings.borrow().lines.window_gaps;
+        match name {
+            "tabnew" | "tabe" => self.new_tab(),
+            "tabclose" | "tabc" => self.close_tab(),
+            "tabnext" | "tabn" => match n {
+                Some(n) => self.go_to_tab(n),
+                None => self.switch_tab(1),
+            },
+            "tabprevious" | "tabp" => self.switch_tab(-(n.unwrap_or(1) as i32)),
+            "split" | "sp" => self.split(SplitDirection::Vertical),
+            "vsplit" | "vs" => self.split(SplitDirection::Horizontal),
+            "close" | "clo" => self.kill_pane(),
+            "only" | "on" => self.only(),
+            "wincmd" | "winc" => match args {
+                "h" => self.tabs[self.curr_tab].move_left(gaps),
+                "j" => self.tabs[self.curr_tab].move_down(gaps),
+                "k" => self.tabs[self.curr_tab].move_up(gaps),
+                "l" => self.tabs[self.curr_tab].move_right(gaps),
+                _ => self.log("Invalid usage: wincmd h/j/k/l".to_string()),
+            },
+            "bnew" | "enew" => self.new_buffer(),
+            "bnext" | "bn" => self.switch_buffer(n.unwrap_or(1) as i32),
+            "bprevious" | "bp" => self.switch_buffer(-(n.unwrap_or(1) as i32)),
+            "bdelete" | "bd" => match n {
+                Some(n) if !(1..=self.buffers.len()).contains(&n) => {
+                    self.log(format!("No buffer {n}"));
                 }
-                'j' => {
-                    self.tabs[self.curr_tab].move_down(self.settings.borrow().lines.window_gaps);
+                Some(n) => self.kill_buffer(n - 1),
+                None => self.kill_buffer(self.curr_buffer()),
+            },
+            "buffer" | "b" => match n {
+                Some(n) => self.go_to_buffer(n),
+                None => self.log("Invalid usage: buffer <n>".to_string()),
+            },
+            "ls" | "buffers" => self.list_buffers(),
+            "buffermode" => self.buffer_mode(),
+            "redraw" | "redr" => self.resize(),
+            "setlocal" | "setl" => self.set_local(args),
+            "source" | "so" => self.source(args),
+            _ => self.log(format!("Invalid command: {name}")),
+        }
+    }
+
+    fn handle_input(&mut self) {
+        let Some(key) = poll_key() else {
+            return;
+        };
+        let mode = match self.mode {
+            Mode::Buffer => "buffer",
+            Mode::Normal => "frame",
+        };
+        let action = self
+            .settings
+            .borrow()
+            .keymaps
+            .get(&(mode.to_string(), key.encode().to_string()))
+            .cloned();
+        match self.mode {
+            Mode::Buffer => {
+                // unbound keys keep waiting, like before
+                if let Some(action) = action {
+                    self.mode = Mode::Normal;
+                    self.run(&action);
+                } else if key == Key::Code(KeyCode::Escape) {
+                    self.mode = Mode::Normal;
                 }
-                'x' => self.kill_pane(),
-                't' => self.new_tab(),
-                'w' => self.close_tab(),
-                'b' => self.buffer_mode(),
-                _ => (),
             }
+            Mode::Normal => match action {
+                Some(action) => self.run(&action),
+                None => {
+                    let curr = self.curr_buffer();
+                    self.buffers[curr].press(key);
+                    self.run_frame_cmds(curr);
+                }
+            },
+        }
+    }
+
+    /// Runs a keymap's action in the selected buffer, so it can use sudoku
+    /// commands as well as frame ones.
+    fn run(&mut self, action: &str) {
+        let curr = self.curr_buffer();
+        self.buffers[curr].data.run(action);
+        self.run_frame_cmds(curr);
+    }
+
+    fn run_frame_cmds(&mut self, buffer: usize) {
+        for cmd in self.buffers[buffer].data.take_frame_cmds() {
+            self.process_cmd(&cmd);
+        }
+        // what the app writes itself is already in the settings
+        if self.buffers[buffer].data.take_wrote_config() {
+            self.config_mtimes = self.config_mtimes();
         }
     }
 
@@ -282,7 +610,7 @@ impl Frame {
         for x in &self.tabs {
             let len = measure_text(
                 &x.name,
-                Some(&self.settings.borrow().font),
+                Some(self.settings.borrow().font()),
                 self.settings.borrow().opts.tabline_font_size,
                 FONT_SCALE,
             )
@@ -301,8 +629,9 @@ impl Frame {
             max,
             tab_size,
         );
+        let settings = self.settings.borrow();
         let text_params: TextParams = TextParams {
-            font: Some(&self.settings.borrow().font),
+            font: Some(settings.font()),
             font_size: self.settings.borrow().opts.tabline_font_size,
             font_scale: FONT_SCALE,
             color: self.settings.borrow().colors.inactive_tab_font,
@@ -320,7 +649,7 @@ impl Frame {
                 );
                 let text_pos = center_text(
                     &tab.name,
-                    &self.settings.borrow().font,
+                    self.settings.borrow().font(),
                     self.settings.borrow().opts.tabline_font_size,
                     rect,
                 );
@@ -336,7 +665,7 @@ impl Frame {
                 );
                 let text_pos = center_text(
                     &tab.name,
-                    &self.settings.borrow().font,
+                    self.settings.borrow().font(),
                     self.settings.borrow().opts.tabline_font_size,
                     rect,
                 );
@@ -369,3 +698,58 @@ pub fn center_text(text: &str, font: &Font, font_size: u16, rect: Rect) -> Vec2
 
     (x, y).into()
 }
+
+/// How many lines `draw_popup` shows at most.
+pub const POPUP_LINES: usize = 10;
+
+/// Draws `lines` in a box growing up and to the right from `bottom_left`,
+/// highlighting the `selected` one, in the `bg` and `font` colors.
+pub fn draw_popup(
+    settings: &Settings,
+    lines: &[String],
+    selected: Option<usize>,
+    bottom_left: Vec2,
+    (bg, font): (Color, Color),
+) {
+    let font_size = settings.opts.command_font_size;
+    let line_height = settings.get_cmd_size() / 2.;
+    // scroll so the selected line stays visible
+    let first = selected.map_or(0, |x| (x + 1).saturating_sub(POPUP_LINES));
+    let shown = &lines[first..lines.len().min(first + POPUP_LINES)];
+
+    let width = shown
+        .iter()
+        .map(|x| measure_text(x, Some(settings.font()), font_size, FONT_SCALE).width)
+        .fold(0.0, f32::max)
+        + 8.;
+    let height = line_height * shown.len() as f32;
+    let x = bottom_left.x.min(screen_width() - width).max(0.);
+    let mut rect = Rect::new(x, bottom_left.y - height, width, line_height);
+    draw_rectangle(rect.x, rect.y, width, height, bg);
+    for (i, line) in shown.iter().enumerate() {
+        if selected == Some(first + i) {
+            draw_rectangle(
+                rect.x,
+                rect.y,
+                rect.w,
+                rect.h,
+                settings.colors.highlight_color,
+            );
+        }
+        let pos = center_text(line, settings.font(), font_size, rect);
+        let params = TextParams {
+            font: Some(settings.font()),
+            font_size,
+            font_scale: FONT_SCALE,
+            color: font,
+            ..Default::default()
+        };
+        draw_text_ex(line, rect.x + 4., pos.y, params);
+        rect.y += line_height;
+    }
+    draw_rect_outlines(
+        Rect::new(x, bottom_left.y - height, width, height),
+        1.,
+        font,
+    );
+}
@@ -150,7 +150,6 @@ impl Split {
     pub fn iter(&self) -> SplitIterator<'_> {
         SplitIterator::new(self)
     }
-    #[allow(dead_code)]
     pub fn iter_mut(&mut self) -> SplitIteratorMut<'_> {
         SplitIteratorMut::new(self)
     }
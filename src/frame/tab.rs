@@ -26,14 +26,24 @@ impl Tab {
         }
     }
 
+    /// Closes every window but the selected one.
+    pub fn only(&mut self) {
+        if let Split::Window(win) = &self[self.selected] {
+            self.inner = Split::Window(win.clone());
+            self.windows = 1;
+            self.selected = 0;
+        }
+    }
+
     pub fn split(&mut self, direction: SplitDirection) {
-        match self[self.selected] {
+        match &self[self.selected] {
             Split::Split(..) => unreachable!(),
+            // the new window starts off like the old one, local options and all
             Split::Window(win) => {
                 let win = win.clone();
                 let idx = self.selected;
                 self[idx] = Split::Split(
-                    Box::new(Split::Window(win)),
+                    Box::new(Split::Window(win.clone())),
                     Box::new(Split::Window(win)),
                     0.5,
                     direction,
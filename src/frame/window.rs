@@ -4,10 +4,13 @@ use macroquad::prelude::*;
 
 use buffer::Buffer;
 
-#[derive(Default, Debug, Clone, Copy)]
+use crate::settings::options::LocalOpts;
+
+#[derive(Default, Debug, Clone)]
 pub struct Window {
     pub dimensions: Rect,
     pub buffer_index: usize,
+    pub local_opts: LocalOpts,
 }
 
 impl Window {
@@ -15,14 +18,11 @@ impl Window {
         Self {
             dimensions,
             buffer_index,
+            local_opts: LocalOpts::default(),
         }
     }
 
     pub fn render(&self, buffer: &Buffer) {
-        buffer.draw(&self.dimensions);
-    }
-
-    pub fn update(&self, buffer: &mut Buffer) {
-        buffer.update();
+        buffer.draw(&self.dimensions, &self.local_opts);
     }
 }
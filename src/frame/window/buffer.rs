@@ -2,22 +2,34 @@ use std::{cell::RefCell, rc::Rc};
 
 use macroquad::prelude::*;
 
-use crate::{settings::Settings, sudoku::Sudoku};
+use crate::{
+    cmd_history::CmdHistory,
+    input::Key,
+    messages::Messages,
+    registers::Registers,
+    settings::{Settings, options::LocalOpts},
+    sudoku::Sudoku,
+};
 
 pub struct Buffer {
     pub data: Sudoku,
 }
 
 impl Buffer {
-    pub fn new(settings: Rc<RefCell<Settings>>) -> Self {
+    pub fn new(
+        settings: Rc<RefCell<Settings>>,
+        registers: Rc<RefCell<Registers>>,
+        cmd_history: Rc<RefCell<CmdHistory>>,
+        messages: Rc<RefCell<Messages>>,
+    ) -> Self {
         Self {
-            data: Sudoku::new(settings),
+            data: Sudoku::new(settings, registers, cmd_history, messages),
         }
     }
-    pub fn draw(&self, dimensions: &Rect) {
-        self.data.draw(*dimensions);
+    pub fn draw(&self, dimensions: &Rect, window_opts: &LocalOpts) {
+        self.data.draw(*dimensions, window_opts);
     }
-    pub fn update(&mut self) {
-        self.data.update();
+    pub fn press(&mut self, key: Key) {
+        self.data.press(key);
     }
 }
@@ -0,0 +1,166 @@
+pub mod notation;
+
+use macroquad::prelude::*;
+
+pub const SHIFT: u8 = 1;
+pub const CTRL: u8 = 2;
+pub const ALT: u8 = 4;
+
+/// Special keys, in the order they're encoded in.
+const CODES: &[KeyCode] = &[
+    KeyCode::Escape,
+    KeyCode::Enter,
+    KeyCode::Tab,
+    KeyCode::Backspace,
+    KeyCode::Delete,
+    KeyCode::Left,
+    KeyCode::Right,
+    KeyCode::Up,
+    KeyCode::Down,
+    KeyCode::Home,
+    KeyCode::End,
+    KeyCode::PageUp,
+    KeyCode::PageDown,
+    KeyCode::Insert,
+];
+
+/// Special keys, with and without modifiers, are encoded as chars from here
+/// on, 32 per combination of modifiers.
+const CODE_BASE: u32 = 0xe000;
+/// Characters held with control or alt are encoded from here on, 128 per
+/// combination of modifiers.
+const MOD_BASE: u32 = 0xe100;
+
+/// A single key press, as fed to the sudoku input handlers and stored in
+/// macro registers.
+///
+/// Keys with modifiers held are `Char`s holding their encoding (see
+/// `Key::encode`), except control with a letter, which gives the usual
+/// control character.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Key {
+    Char(char),
+    Code(KeyCode),
+}
+
+impl Key {
+    /// A special key with `mods` held.
+    pub fn code(kc: KeyCode, mods: u8) -> Self {
+        if mods == 0 {
+            return Self::Code(kc);
+        }
+        let idx = CODES.iter().position(|x| *x == kc).unwrap_or(0) as u32;
+        Self::Char(char::from_u32(CODE_BASE + mods as u32 * 32 + idx).unwrap())
+    }
+
+    /// A character with `mods` held. Shift is expected to already be applied
+    /// to `c`.
+    pub fn char(c: char, mods: u8) -> Self {
+        let mods = mods & !SHIFT;
+        if mods == CTRL && c.is_ascii_alphabetic() {
+            return Self::Char(ctrl(c));
+        }
+        if mods == 0 || !c.is_ascii() {
+            return Self::Char(c);
+        }
+        Self::Char(char::from_u32(MOD_BASE + (mods as u32 / 2 - 1) * 128 + c as u32).unwrap())
+    }
+
+    /// The key as a single char, the way keymaps store it.
+    pub fn encode(self) -> char {
+        match self {
+            Self::Char(c) => c,
+            Self::Code(kc) => {
+                let idx = CODES.iter().position(|x| *x == kc).unwrap_or(0) as u32;
+                char::from_u32(CODE_BASE + idx).unwrap()
+            }
+        }
+    }
+
+    pub fn decode(c: char) -> Self {
+        match decode(c) {
+            Decoded::Code(kc, 0) => Self::Code(kc),
+            _ => Self::Char(c),
+        }
+    }
+}
+
+/// What an encoded char stands for.
+pub enum Decoded {
+    Char(char, u8),
+    Code(KeyCode, u8),
+}
+
+pub fn decode(c: char) -> Decoded {
+    let n = c as u32;
+    if (CODE_BASE..MOD_BASE).contains(&n) {
+        let n = n - CODE_BASE;
+        match CODES.get((n % 32) as usize) {
+            Some(kc) => Decoded::Code(*kc, (n / 32) as u8),
+            None => Decoded::Char(c, 0),
+        }
+    } else if (MOD_BASE..MOD_BASE + 3 * 128).contains(&n) {
+        let n = n - MOD_BASE;
+        let c = char::from_u32(n % 128).unwrap();
+        Decoded::Char(c, (n / 128 + 1) as u8 * 2)
+    } else if c.is_ascii_control() && c != '\x7f' {
+        Decoded::Char(((c as u8) | 0x60) as char, CTRL)
+    } else {
+        Decoded::Char(c, 0)
+    }
+}
+
+/// Polls macroquad for the next key press, along with the modifiers held. The
+/// special keys take precedence over queued characters.
+pub fn poll_key() -> Option<Key> {
+    let mut mods = 0;
+    if is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift) {
+        mods |= SHIFT;
+    }
+    if is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl) {
+        mods |= CTRL;
+    }
+    if is_key_down(KeyCode::LeftAlt) || is_key_down(KeyCode::RightAlt) {
+        mods |= ALT;
+    }
+    if let Some(kc) = get_last_key_pressed()
+        && CODES.contains(&kc)
+    {
+        // the character some of these also queue is dropped below
+        return Some(Key::code(kc, mods));
+    }
+    let c = get_char_pressed().filter(|c| !c.is_control())?;
+    // AltGr shows up as control and alt, but the character is what was meant
+    if mods & (CTRL | ALT) == CTRL | ALT && !c.is_ascii_alphanumeric() {
+        return Some(Key::Char(c));
+    }
+    Some(Key::char(c, mods))
+}
+
+/// The control character typed by holding control with `c`, e.g. `^O`.
+pub fn ctrl(c: char) -> char {
+    (c.to_ascii_uppercase() as u8 & 0x1f) as char
+}
+
+pub fn keys_from_str(str: &str) -> Vec<Key> {
+    str.chars().map(Key::decode).collect()
+}
+
+#[test]
+fn key_encoding_works() {
+    for key in [
+        Key::Char('a'),
+        Key::Code(KeyCode::Escape),
+        Key::code(KeyCode::Tab, SHIFT | CTRL),
+        Key::char('5', ALT),
+        Key::char('-', CTRL),
+    ] {
+        assert_eq!(Key::decode(key.encode()), key);
+    }
+    assert_eq!(Key::char('v', CTRL), Key::Char('\u{16}'));
+    assert!(matches!(decode('\u{16}'), Decoded::Char('v', CTRL)));
+    assert!(matches!(
+        decode(Key::char('-', CTRL | ALT).encode()),
+        Decoded::Char('-', 6)
+    ));
+}
@@ -0,0 +1,138 @@
+//! Vim style key notation, e.g. `<C-w>`, `<S-Tab>`, `<A-5>`, `<lt>` or
+//! `<leader>`, for writing keymaps.
+
+use macroquad::prelude::KeyCode;
+
+use crate::input::{ALT, CTRL, Decoded, Key, SHIFT, decode};
+
+/// Names of special keys, the first one being the one printed.
+const NAMES: &[(&str, KeyCode)] = &[
+    ("Esc", KeyCode::Escape),
+    ("CR", KeyCode::Enter),
+    ("Enter", KeyCode::Enter),
+    ("Return", KeyCode::Enter),
+    ("Tab", KeyCode::Tab),
+    ("BS", KeyCode::Backspace),
+    ("Del", KeyCode::Delete),
+    ("Left", KeyCode::Left),
+    ("Right", KeyCode::Right),
+    ("Up", KeyCode::Up),
+    ("Down", KeyCode::Down),
+    ("Home", KeyCode::Home),
+    ("End", KeyCode::End),
+    ("PageUp", KeyCode::PageUp),
+    ("PageDown", KeyCode::PageDown),
+    ("Insert", KeyCode::Insert),
+];
+
+/// Names of characters that are awkward to write as they are.
+const CHAR_NAMES: &[(&str, char)] = &[("Space", ' '), ("lt", '<'), ("Bar", '|'), ("Bslash", '\\')];
+
+/// Parses a key sequence into the chars keymaps store, see `Key::encode`.
+/// `<leader>` stands for the already parsed `leader`.
+pub fn parse(str: &str, leader: &str) -> Result<String, String> {
+    let mut res = String::new();
+    let mut rest = str;
+    while let Some(c) = rest.chars().next() {
+        let special = rest
+            .strip_prefix('<')
+            .and_then(|x| x.find('>').map(|end| (&x[..end], &x[end + 1..])));
+        match special {
+            // `<>` or a lone `<` is just a `<`
+            Some((inner, after)) if inner.eq_ignore_ascii_case("leader") => {
+                res += leader;
+                rest = after;
+            }
+            Some((inner, after)) if !inner.is_empty() => {
+                res.push(parse_special(inner)?.encode());
+                rest = after;
+            }
+            _ => {
+                res.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    Ok(res)
+}
+
+fn parse_special(inner: &str) -> Result<Key, String> {
+    let mut mods = 0;
+    let mut name = inner;
+    while let [m, b'-', _, ..] = name.as_bytes() {
+        mods |= match m.to_ascii_uppercase() {
+            b'S' => SHIFT,
+            b'C' => CTRL,
+            b'A' | b'M' => ALT,
+            _ => break,
+        };
+        name = &name[2..];
+    }
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        let c = if mods & SHIFT > 0 {
+            c.to_ascii_uppercase()
+        } else {
+            c
+        };
+        return Ok(Key::char(c, mods));
+    }
+    if let Some((_, c)) = CHAR_NAMES
+        .iter()
+        .find(|(x, _)| x.eq_ignore_ascii_case(name))
+    {
+        return Ok(Key::char(*c, mods));
+    }
+    match NAMES.iter().find(|(x, _)| x.eq_ignore_ascii_case(name)) {
+        Some((_, kc)) => Ok(Key::code(*kc, mods)),
+        None => Err(format!("Unknown key: <{inner}>")),
+    }
+}
+
+/// Prints keymap chars back in key notation.
+pub fn pretty(str: &str) -> String {
+    let mut res = String::new();
+    for c in str.chars() {
+        let (name, mods) = match decode(c) {
+            Decoded::Code(kc, mods) => {
+                let name = NAMES.iter().find(|(_, x)| *x == kc).map_or("?", |x| x.0);
+                (name.to_string(), mods)
+            }
+            Decoded::Char(c, mods) => match c {
+                ' ' => ("Space".to_string(), mods),
+                '<' => ("lt".to_string(), mods),
+                _ if mods == 0 => {
+                    res.push(c);
+                    continue;
+                }
+                _ => (c.to_string(), mods),
+            },
+        };
+        res.push('<');
+        for (m, prefix) in [(CTRL, "C-"), (ALT, "A-"), (SHIFT, "S-")] {
+            if mods & m > 0 {
+                res += prefix;
+            }
+        }
+        res += &name;
+        res.push('>');
+    }
+    res
+}
+
+#[test]
+fn notation_works() {
+    let parsed = parse("<C-w>a<S-Tab><lt><A-5><C-Bslash> <>", "").unwrap();
+    assert_eq!(parsed.chars().next(), Some('\u{17}'));
+    assert_eq!(pretty(&parsed), "<C-w>a<S-Tab><lt><A-5><C-\\><Space><lt>>");
+    assert_eq!(parse("<c-V>", "").unwrap(), "\u{16}");
+    assert_eq!(
+        parse("<C-S-tab>", "").unwrap(),
+        parse("<S-C-Tab>", "").unwrap()
+    );
+    assert_eq!(
+        pretty(&parse("<leader>x<CR>", " ").unwrap()),
+        "<Space>x<CR>"
+    );
+    assert!(parse("<Nope>", "").is_err());
+}
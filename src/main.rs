@@ -1,13 +1,16 @@
+mod cmd_history;
 mod frame;
+mod input;
+mod messages;
+mod registers;
 mod settings;
 mod sudoku;
 mod unwrap_or_else;
 
-use std::{process::exit};
+use std::process::exit;
 
-use directories::ProjectDirs;
 use macroquad::prelude::*;
-use settings::{Settings, config::Config};
+use settings::{Settings, config};
 
 use crate::frame::Frame;
 
@@ -22,15 +25,42 @@ fn window_conf() -> Conf {
     }
 }
 
-#[macroquad::main(window_conf())]
-async fn main() {
-    let config = if let Ok(val) = load_config() {
-        Some(val)
-    } else {
-        None
-    };
-    let settings = Settings::from_config(&config);
-    let mut frame = Frame::new(settings);
+const USAGE: &str = "Usage: sucocku [--set table.key=value]... [--mkconfig]";
+
+fn main() {
+    let mut overrides = vec![];
+    let mut mkconfig = false;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--mkconfig" => mkconfig = true,
+            "--set" => match args.next() {
+                Some(x) => overrides.push(x),
+                None => {
+                    eprintln!("--set needs a table.key=value\n{USAGE}");
+                    exit(1)
+                }
+            },
+            _ => {
+                eprintln!("Unknown argument: {arg}\n{USAGE}");
+                exit(1)
+            }
+        }
+    }
+    if mkconfig {
+        let (settings, problems) = Settings::load(&overrides);
+        for problem in problems {
+            eprintln!("{problem}");
+        }
+        print!("{}", config::generate(&settings));
+        return;
+    }
+    macroquad::Window::from_config(window_conf(), run(overrides));
+}
+
+async fn run(overrides: Vec<String>) {
+    let (settings, problems) = Settings::load(&overrides);
+    let mut frame = Frame::new(settings, problems, overrides);
 
     loop {
         frame.draw();
@@ -39,28 +69,6 @@ async fn main() {
     }
 }
 
-fn load_config() -> std::io::Result<Config> {
-    let prj_dirs = &ProjectDirs::from("com", "Taice", "Sucocku").unwrap();
-    let config_dir = directories::ProjectDirs::config_dir(&prj_dirs);
-    let file = config_dir.join("config.toml");
-    let content = match std::fs::read_to_string(&file) {
-        Ok(x) => x,
-        Err(e) => {
-            let _ = std::fs::write(file, include_str!("../assets/config.toml"));
-            println!("{e}");
-            exit(1)
-        }
-    };
-    let config: Config = match toml::from_str(&content) {
-        Ok(x) => x,
-        Err(e) => {
-            println!("{e}");
-            exit(1)
-        }
-    };
-    Ok(config)
-}
-
 pub fn draw_rect_outlines(r: Rect, t: f32, color: Color) {
     draw_rectangle(r.x, r.y, t, r.h, color);
     draw_rectangle(r.x, r.y, r.w, t, color);
@@ -0,0 +1,52 @@
+use std::collections::VecDeque;
+
+const MESSAGES_LEN: usize = 200;
+
+/// Everything shown on the command line, shared by every buffer, for
+/// `:messages`.
+#[derive(Default)]
+pub struct Messages {
+    lines: VecDeque<String>,
+    /// How many messages were ever pushed.
+    count: usize,
+}
+
+impl Messages {
+    pub fn push(&mut self, msg: String) {
+        self.lines.push_back(msg);
+        self.count += 1;
+        if self.lines.len() > MESSAGES_LEN {
+            self.lines.pop_front();
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Removes and returns the messages pushed since `count` was taken.
+    pub fn take_since(&mut self, count: usize) -> Vec<String> {
+        let new = (self.count - count).min(self.lines.len());
+        self.count -= new;
+        self.lines.drain(self.lines.len() - new..).collect()
+    }
+
+    pub fn lines(&self) -> impl DoubleEndedIterator<Item = &String> {
+        self.lines.iter()
+    }
+
+    pub fn clear(&mut self) {
+        self.lines.clear();
+    }
+}
+
+#[test]
+fn messages_work() {
+    let mut messages = Messages::default();
+    messages.push("a".to_string());
+    let count = messages.count();
+    messages.push("b".to_string());
+    messages.push("c".to_string());
+    assert_eq!(messages.take_since(count), ["b", "c"]);
+    assert_eq!(messages.lines().collect::<Vec<_>>(), ["a"]);
+}
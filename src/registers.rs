@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+
+use crate::input::Key;
+
+pub const UNNAMED: char = '"';
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Register {
+    Keys(Vec<Key>),
+    /// Cell values keyed by their offset from the cursor they were yanked at.
+    Cells(Vec<((i8, i8), u16)>),
+}
+
+/// Named registers shared between every buffer of the frame.
+#[derive(Default)]
+pub struct Registers {
+    registers: HashMap<char, Register>,
+    pub last_played: Option<char>,
+}
+
+impl Registers {
+    pub fn new(keys: HashMap<char, Vec<Key>>) -> Self {
+        Self {
+            registers: keys
+                .into_iter()
+                .map(|(name, keys)| (name, Register::Keys(keys)))
+                .collect(),
+            last_played: None,
+        }
+    }
+
+    pub fn is_valid_name(name: char) -> bool {
+        name.is_ascii_alphanumeric() || name == UNNAMED
+    }
+
+    pub fn get(&self, name: char) -> Option<&Register> {
+        self.registers.get(&name.to_ascii_lowercase())
+    }
+
+    /// Uppercase names append to their lowercase register, like in vim.
+    pub fn set(&mut self, name: char, register: Register) {
+        let lower = name.to_ascii_lowercase();
+        if name.is_ascii_uppercase()
+            && let Some(old) = self.registers.get_mut(&lower)
+        {
+            match (old, register) {
+                (Register::Keys(old), Register::Keys(new)) => old.extend(new),
+                (Register::Cells(old), Register::Cells(new)) => old.extend(new),
+                (old, new) => *old = new,
+            }
+        } else {
+            self.registers.insert(lower, register);
+        }
+    }
+}
+
+#[test]
+fn uppercase_register_appends() {
+    let mut registers = Registers::default();
+    registers.set('a', Register::Keys(vec![Key::Char('h')]));
+    registers.set('A', Register::Keys(vec![Key::Char('j')]));
+    assert_eq!(
+        registers.get('a'),
+        Some(&Register::Keys(vec![Key::Char('h'), Key::Char('j')]))
+    );
+    assert_eq!(registers.get('A'), registers.get('a'));
+
+    registers.set('A', Register::Cells(vec![((0, 0), 5)]));
+    assert_eq!(
+        registers.get('a'),
+        Some(&Register::Cells(vec![((0, 0), 5)]))
+    );
+}
@@ -1,11 +1,23 @@
 pub mod colors;
+pub mod colorscheme;
 pub mod config;
 pub mod lines;
+pub mod options;
 pub mod opts;
 
-use crate::unwrap_or_else;
+use crate::{
+    frame,
+    input::{Key, keys_from_str, notation},
+    registers::Registers,
+    sudoku::{is_command, mode},
+    unwrap_or_else,
+};
 
-use std::collections::HashMap;
+use std::{
+    cell::OnceCell,
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 
 use colors::Colors;
 use config::Config;
@@ -21,13 +33,6 @@ macro_rules! assign_if_some {
         }
     };
 }
-macro_rules! assign_if_some_map {
-    ($target:expr, $opt:expr, $map:expr) => {
-        if let Some(val) = $opt {
-            $target = $map(val);
-        }
-    };
-}
 
 pub const FONT_SCALE: f32 = 0.5;
 const BASE_NUM_FONT_SIZE: u16 = 122;
@@ -41,15 +46,43 @@ pub struct Settings {
     pub lines: Lines,
     pub colors: Colors,
     pub opts: Opts,
-    pub font: Font,
+    /// Loaded on first use, so settings can be made without a window.
+    font: OnceCell<Font>,
     pub keymaps: HashMap<(String, String), String>,
+    pub registers: HashMap<char, Vec<Key>>,
+    /// The colorscheme `colors` started from.
+    pub colorscheme: String,
+    /// The config files and includes `load` read, to watch for changes.
+    pub config_files: Vec<PathBuf>,
 }
 
 impl Settings {
-    pub fn from_config(config: &Option<Config>) -> Self {
-        let font = load_ttf_font_from_bytes(include_bytes!("../assets/Roboto-Regular.ttf"))
-            .expect("WTF you do bro.");
-        let mut default = Self::new(font);
+    /// The settings from every config layer and `overrides`, see
+    /// `config::load_layers`, and everything wrong with them.
+    pub fn load(overrides: &[String]) -> (Self, Vec<String>) {
+        let (config, mut problems, config_files) = config::load_layers(overrides);
+        let (mut settings, settings_problems) = Self::from_config(&config);
+        problems.extend(settings_problems);
+        settings.config_files = config_files;
+        (settings, problems)
+    }
+
+    /// Like `load`, with `file` merged over the layers, see
+    /// `config::load_over_layers`.
+    pub fn load_over(file: &Path, overrides: &[String]) -> Result<(Self, Vec<String>), String> {
+        let (config, mut problems, config_files) = config::load_over_layers(file, overrides)?;
+        let (mut settings, settings_problems) = Self::from_config(&Some(config));
+        problems.extend(settings_problems);
+        settings.config_files = config_files;
+        Ok((settings, problems))
+    }
+
+    /// The settings with `config` applied, skipping the parts that don't
+    /// parse and returning what was wrong with them.
+    pub fn from_config(config: &Option<Config>) -> (Self, Vec<String>) {
+        let mut default = Self::new();
+        let mut problems = vec![];
+        default.keymaps = default_keymaps();
         if let Some(config) = config {
             if let Some(lines) = &config.lines {
                 assign_if_some!(default.lines.outer_width, lines.outer_line_width);
@@ -58,53 +91,18 @@ impl Settings {
                 assign_if_some!(default.lines.window_gaps, lines.window_gaps);
             }
 
+            if let Some(name) = &config.colorscheme {
+                match colorscheme::load(name) {
+                    Ok((colors, warnings)) => {
+                        default.colors = colors;
+                        default.colorscheme = name.clone();
+                        problems.extend(warnings);
+                    }
+                    Err(err_msg) => problems.push(err_msg),
+                }
+            }
             if let Some(colors) = &config.colors {
-                let into = |c: [f32; 4]| Color {
-                    r: c[0],
-                    g: c[1],
-                    b: c[2],
-                    a: c[3],
-                };
-                assign_if_some_map!(default.colors.square_color, colors.square_color, into);
-                assign_if_some_map!(default.colors.bg_color, colors.bg_color, into);
-                assign_if_some_map!(default.colors.outer_color, colors.outer_line, into);
-                assign_if_some_map!(default.colors.box_color, colors.box_line, into);
-                assign_if_some_map!(default.colors.normal_color, colors.normal_line, into);
-                assign_if_some_map!(default.colors.window_gaps, colors.window_gaps, into);
-                assign_if_some_map!(default.colors.selected_window, colors.selected_window, into);
-                assign_if_some_map!(default.colors.selected_tab, colors.selected_tab, into);
-                assign_if_some_map!(
-                    default.colors.selected_tab_font,
-                    colors.selected_tab_font,
-                    into
-                );
-                assign_if_some_map!(
-                    default.colors.inactive_tab_font,
-                    colors.inactive_tab_font,
-                    into
-                );
-                assign_if_some_map!(
-                    default.colors.inactive_tab_color,
-                    colors.inactive_tab_color,
-                    into
-                );
-
-                assign_if_some_map!(default.colors.normal_font, colors.normal_font_color, into);
-                assign_if_some_map!(default.colors.note_font, colors.note_font_color, into);
-                assign_if_some_map!(default.colors.cmd_font, colors.cmd_font_color, into);
-                assign_if_some_map!(default.colors.status_font, colors.status_font_color, into);
-
-                assign_if_some_map!(default.colors.cmd_bg, colors.cmd_bg_color, into);
-                assign_if_some_map!(default.colors.status_bg, colors.status_bg_color, into);
-
-                assign_if_some_map!(default.colors.highlight_color, colors.highlight_color, into);
-                assign_if_some_map!(
-                    default.colors.visual_highlight_color,
-                    colors.visual_highlight_color,
-                    into
-                );
-
-                assign_if_some_map!(default.colors.invalid_color, colors.invalid_color, into);
+                default.colors.apply(colors);
             }
 
             if let Some(o) = &config.opts {
@@ -113,6 +111,7 @@ impl Settings {
                 assign_if_some!(default.opts.tabline_font_size, o.tabline_font_size);
                 assign_if_some!(default.opts.tabline_gap, o.tabline_gap);
                 assign_if_some!(default.opts.visual_highlight_size, o.visual_highlight_size);
+                assign_if_some!(default.opts.note_size, o.note_size);
                 assign_if_some!(
                     default.opts.auto_candidate_elimination,
                     o.auto_candidate_elimination
@@ -124,22 +123,35 @@ impl Settings {
                     o.highlight_square_instead_of_note
                 );
                 assign_if_some!(default.opts.remove_invalid, o.remove_invalid);
+                assign_if_some!(default.opts.wrap_around, o.wrap_around);
+                assign_if_some!(default.opts.persist_keymaps, o.persist_keymaps);
+                assign_if_some!(default.opts.timeoutlen, o.timeoutlen);
+                assign_if_some!(default.opts.which_key, o.which_key);
+                assign_if_some!(default.opts.which_key_delay, o.which_key_delay);
+                if let Some(leader) = &o.leader {
+                    match notation::parse(leader, "") {
+                        Ok(leader) => default.opts.leader = leader,
+                        Err(e) => problems.push(format!("opts.leader: {e}")),
+                    }
+                }
             }
             if let Some(keymaps) = &config.keymaps {
-                default.keymaps = match parse_config_keymaps(&keymaps) {
-                    Ok(x) => x,
-                    Err(err_msg) => {
-                        eprintln!("{err_msg}");
-                        std::process::exit(1);
+                let keymaps = parse_config_keymaps(keymaps, &default.opts.leader, &mut problems);
+                for (key, action) in keymaps {
+                    if action.is_empty() {
+                        default.keymaps.remove(&key);
+                    } else {
+                        default.keymaps.insert(key, action);
                     }
-                };
-            } else {
-                default.keymaps = default_keymaps();
+                }
+            }
+            if let Some(registers) = &config.registers {
+                default.registers = parse_config_registers(registers, &mut problems);
             }
         }
-        default
+        (default, problems)
     }
-    pub fn new(font: Font) -> Self {
+    pub fn new() -> Self {
         let lines = Lines::default();
         let colors = Colors::default();
         let opts = Opts::default();
@@ -147,12 +159,18 @@ impl Settings {
             colors,
             lines,
             opts,
-            font,
+            font: OnceCell::new(),
             keymaps: HashMap::new(),
+            registers: HashMap::new(),
+            colorscheme: "light".to_string(),
+            config_files: vec![],
         }
     }
-    pub fn get_highlight_size(&self, box_size: f32) -> f32 {
-        self.opts.visual_highlight_size * (box_size / BASE_BOX_SIZE)
+    pub fn font(&self) -> &Font {
+        self.font.get_or_init(|| {
+            load_ttf_font_from_bytes(include_bytes!("../assets/Roboto-Regular.ttf"))
+                .expect("WTF you do bro.")
+        })
     }
     pub fn get_lengths(&self, min_size: f32) -> (f32, f32) {
         let offset = self.lines.outer_width * 2.0
@@ -167,9 +185,6 @@ impl Settings {
     pub fn get_num_font_size(&self, box_size: f32) -> u16 {
         (BASE_NUM_FONT_SIZE as f32 * (box_size / BASE_BOX_SIZE)) as u16
     }
-    pub fn get_note_font_size(&self, box_size: f32) -> u16 {
-        (BASE_NOTE_FONT_SIZE as f32 * (box_size / BASE_BOX_SIZE)) as u16
-    }
 
     pub fn get_cmd_size(&self) -> f32 {
         ((self.opts.command_font_size as f32 / BASE_COMMAND_FONT_SIZE as f32) * 22. * 2.).ceil()
@@ -214,42 +229,263 @@ fn default_keymaps() -> HashMap<(String, String), String> {
     new_keymap!(hmap, nm, n, i, h; "j" => "move down");
     new_keymap!(hmap, nm, n, i, h; "k" => "move up");
     new_keymap!(hmap, nm, n, i, h; "l" => "move right");
+    new_keymap!(hmap, nm, n, i, h; keys("<Left>") => "move left");
+    new_keymap!(hmap, nm, n, i, h; keys("<Down>") => "move down");
+    new_keymap!(hmap, nm, n, i, h; keys("<Up>") => "move up");
+    new_keymap!(hmap, nm, n, i, h; keys("<Right>") => "move right");
     new_keymap!(hmap, nm, n, i, h, g; " " => "mark");
 
+    new_keymap!(hmap, nm, n, i, h; "w" => "move next-box");
+    new_keymap!(hmap, nm, n, i, h; "b" => "move prev-box");
+    new_keymap!(hmap, nm, n, i, h; "B" => "move box");
+    new_keymap!(hmap, nm, n, i, h; "}" => "move next-empty");
+    new_keymap!(hmap, nm, n, i, h; "{" => "move prev-empty");
+    new_keymap!(hmap, nm, n, i, h; ")" => "move next-candidates");
+    new_keymap!(hmap, nm, n, i, h; "(" => "move prev-candidates");
+    new_keymap!(hmap, nm, n, i, h; "$" => "move row-end");
+    new_keymap!(hmap, nm, n, i, h; "G" => "move col-end");
+    new_keymap!(hmap, nm; "0" => "move row-start");
+    new_keymap!(hmap, g; "g" => "move col-start; mode normal");
+
     new_keymap!(hmap, nm, n, i, h; "g" => "go");
     new_keymap!(hmap, nm, n, g, h; "i" => "insert");
-    new_keymap!(hmap, nm, i, g, h, "visual"; "n" => "note");
-    new_keymap!(hmap, nm, i, n, g, "visual"; "H" => "highlight");
+    new_keymap!(hmap, nm, i, g, h; "n" => "note");
+    new_keymap!(hmap, nm, i, n, g; "H" => "highlight");
+
+    let v = "visual";
+    let vb = "visual-block";
+    let vr = "visual-row";
+    let vc = "visual-col";
+    let vx = "visual-box";
+    new_keymap!(hmap, nm, n, i, h, v, vb, vr, vc, vx; "v" => "visual path");
+    new_keymap!(hmap, nm, n, i, h, v, vb, vr, vc, vx; keys("<C-v>") => "visual block");
+    new_keymap!(hmap, nm, n, i, h, v, vb, vr, vc, vx; "V" => "visual row");
+    new_keymap!(hmap, nm, n, i, h, v, vb, vr, vc, vx; "|" => "visual col");
+    new_keymap!(hmap, nm, n, i, h, v, vb, vr, vc, vx; "#" => "visual box");
+    new_keymap!(hmap, g; "v" => "visual reselect");
+    new_keymap!(hmap, v, vb, vr, vc, vx; "o" => "visual swap");
+    new_keymap!(hmap, v, vb, vr, vc, vx; "h" => "move left");
+    new_keymap!(hmap, v, vb, vr, vc, vx; "j" => "move down");
+    new_keymap!(hmap, v, vb, vr, vc, vx; "k" => "move up");
+    new_keymap!(hmap, v, vb, vr, vc, vx; "l" => "move right");
+    new_keymap!(hmap, v, vb, vr, vc, vx; keys("<Left>") => "move left");
+    new_keymap!(hmap, v, vb, vr, vc, vx; keys("<Down>") => "move down");
+    new_keymap!(hmap, v, vb, vr, vc, vx; keys("<Up>") => "move up");
+    new_keymap!(hmap, v, vb, vr, vc, vx; keys("<Right>") => "move right");
+    new_keymap!(hmap, v, vb, vr, vc, vx; "w" => "move next-box");
+    new_keymap!(hmap, v, vb, vr, vc, vx; "b" => "move prev-box");
+    new_keymap!(hmap, v, vb, vr, vc, vx; "B" => "move box");
+    new_keymap!(hmap, v, vb, vr, vc, vx; "}" => "move next-empty");
+    new_keymap!(hmap, v, vb, vr, vc, vx; "{" => "move prev-empty");
+    new_keymap!(hmap, v, vb, vr, vc, vx; ")" => "move next-candidates");
+    new_keymap!(hmap, v, vb, vr, vc, vx; "(" => "move prev-candidates");
+    new_keymap!(hmap, v, vb, vr, vc, vx; "0" => "move row-start");
+    new_keymap!(hmap, v, vb, vr, vc, vx; "$" => "move row-end");
+    new_keymap!(hmap, v, vb, vr, vc, vx; "G" => "move col-end");
+    new_keymap!(hmap, v, vb, vr, vc, vx; "n" => "note");
+    new_keymap!(hmap, v, vb, vr, vc, vx; "i" => "insert");
+    new_keymap!(hmap, v, vb, vr, vc, vx; "x" => "clear; visual exit");
+    new_keymap!(hmap, v, vb, vr, vc, vx; "d" => "clear; visual exit");
+    new_keymap!(hmap, v, vb, vr, vc, vx; "c" => "clear; visual exit; insert");
+    new_keymap!(hmap, v, vb, vr, vc, vx; "y" => "yank; visual exit");
+    new_keymap!(hmap, v, vb, vr, vc, vx; "\"" => "register");
+    new_keymap!(hmap, v, vb, vr, vc, vx; "H" => "highlight");
 
-    new_keymap!(hmap, nm, n, i, h; "v" => "mode visual");
-    new_keymap!(hmap, "visual"; "h" => "mark; move left");
-    new_keymap!(hmap, "visual"; "j" => "mark; move down");
-    new_keymap!(hmap, "visual"; "k" => "mark; move up");
-    new_keymap!(hmap, "visual"; "l" => "mark; move right");
+    new_keymap!(hmap, nm; "Ch" => "cursor add left");
+    new_keymap!(hmap, nm; "Cj" => "cursor add down");
+    new_keymap!(hmap, nm; "Ck" => "cursor add up");
+    new_keymap!(hmap, nm; "Cl" => "cursor add right");
+    new_keymap!(hmap, nm; "Cs" => "cursor selection");
+    new_keymap!(hmap, nm; "Cc" => "cursor clear");
+    new_keymap!(hmap, v, vb, vr, vc, vx; "C" => "cursor selection");
+
+    // search binds go over the normal ones while a search is shown
+    new_keymap!(hmap, nm; "/" => "search");
+    new_keymap!(hmap, "search"; "n" => "search next");
+    new_keymap!(hmap, "search"; "N" => "search prev");
+
+    let o = "operator";
+    new_keymap!(hmap, nm; "d" => "operator clear");
+    new_keymap!(hmap, nm; "c" => "operator change");
+    new_keymap!(hmap, nm; "y" => "operator yank");
+    new_keymap!(hmap, nm; "t" => "operator note");
+    new_keymap!(hmap, o; "d" => "object cell");
+    new_keymap!(hmap, o; "c" => "object cell");
+    new_keymap!(hmap, o; "y" => "object cell");
+    new_keymap!(hmap, o; "t" => "object cell");
+    new_keymap!(hmap, o; "ir" => "object row");
+    new_keymap!(hmap, o; "ic" => "object col");
+    new_keymap!(hmap, o; "ib" => "object box");
+    new_keymap!(hmap, o; "h" => "move left");
+    new_keymap!(hmap, o; "j" => "move down");
+    new_keymap!(hmap, o; "k" => "move up");
+    new_keymap!(hmap, o; "l" => "move right");
+    new_keymap!(hmap, o; "w" => "move next-box");
+    new_keymap!(hmap, o; "b" => "move prev-box");
+    new_keymap!(hmap, o; "}" => "move next-empty");
+    new_keymap!(hmap, o; "{" => "move prev-empty");
+    new_keymap!(hmap, o; "0" => "move row-start");
+    new_keymap!(hmap, o; "$" => "move row-end");
+    new_keymap!(hmap, o; "G" => "move col-end");
+
+    new_keymap!(hmap, nm; "m" => "bookmark");
+    new_keymap!(hmap, nm, n, i, h, "search"; "'" => "jump");
+    new_keymap!(hmap, nm, n, i, h, "search"; keys("<C-o>") => "jump older");
+    new_keymap!(hmap, nm, n, i, h, "search"; keys("<C-i>") => "jump newer");
 
     new_keymap!(hmap, nm; "u" => "undo");
     new_keymap!(hmap, nm; "r" => "redo");
+    new_keymap!(hmap, nm; "x" => "clear");
+    new_keymap!(hmap, nm; "." => "repeat");
+
+    new_keymap!(hmap, nm; "\"" => "register");
+    new_keymap!(hmap, nm; "p" => "put");
+
+    new_keymap!(hmap, nm; "q" => "record");
+    new_keymap!(hmap, nm; "@" => "play");
+
+    let fr = "frame";
+    new_keymap!(hmap, fr; keys("<C-Bslash>") => "split");
+    new_keymap!(hmap, fr; keys("<C-->") => "vsplit");
+    new_keymap!(hmap, fr; keys("<C-h>") => "wincmd h");
+    new_keymap!(hmap, fr; keys("<C-j>") => "wincmd j");
+    new_keymap!(hmap, fr; keys("<C-k>") => "wincmd k");
+    new_keymap!(hmap, fr; keys("<C-l>") => "wincmd l");
+    new_keymap!(hmap, fr; keys("<C-x>") => "close");
+    new_keymap!(hmap, fr; keys("<C-t>") => "tabnew");
+    new_keymap!(hmap, fr; keys("<C-w>") => "tabclose");
+    new_keymap!(hmap, fr; keys("<C-Tab>") => "tabnext");
+    new_keymap!(hmap, fr; keys("<C-S-Tab>") => "tabprevious");
+    new_keymap!(hmap, fr; keys("<C-b>") => "buffermode");
+
+    let bf = "buffer";
+    new_keymap!(hmap, bf; "l" => "bnext");
+    new_keymap!(hmap, bf; "h" => "bprevious");
+    new_keymap!(hmap, bf; "n" => "bnew");
+    new_keymap!(hmap, bf; "k" => "bdelete");
+    new_keymap!(hmap, bf; keys("<Tab>") => "bnext; buffermode");
 
     hmap
 }
 
+/// Keys written in key notation, for the default keymaps.
+fn keys(str: &str) -> String {
+    notation::parse(str, "").unwrap()
+}
+
+/// Parses the config's keymaps, leaving out and reporting the broken ones.
+///
+/// Keys are `modes;keys`, with the keys in key notation. Only the first `;`
+/// separates, so `normal;;` binds `;`.
 fn parse_config_keymaps(
     keymaps: &HashMap<String, String>,
-) -> Result<HashMap<(String, String), String>, &'static str> {
+    leader: &str,
+    problems: &mut Vec<String>,
+) -> HashMap<(String, String), String> {
     let mut res = HashMap::new();
     for (k, action) in keymaps {
-        let mut split = k.split(";");
-        let modes = unwrap_or_else!(split.next(), {
-            return Err("Invalid key");
-        });
-        let bind = unwrap_or_else!(split.next(), {
-            return Err("No mode/keybind specified");
+        let (modes, bind) = unwrap_or_else!(k.split_once(";"), {
+            problems.push(format!("keymaps: No mode/keybind specified: {k}"));
+            continue;
         });
+        if bind.is_empty() {
+            problems.push(format!("keymaps: No keybind specified: {k}"));
+            continue;
+        }
+        let bind = match notation::parse(bind, leader) {
+            Ok(x) => x,
+            Err(e) => {
+                problems.push(format!("keymaps.{k}: {e}"));
+                continue;
+            }
+        };
         for mode in modes.split(",") {
             res.insert((mode.to_string(), bind.to_string()), action.clone());
         }
     }
-    Ok(res)
+    res
+}
+
+/// Whether `mode` can have keymaps: a buffer or frame mode, or one entered by
+/// `keymaps`.
+pub fn is_mode(keymaps: &HashMap<(String, String), String>, mode: &str) -> bool {
+    frame::MODES.contains(&mode)
+        || mode::NAMES.contains(&mode)
+        || custom_modes(keymaps).contains(&mode)
+}
+
+/// The modes `keymaps` enter with `mode`, which exist as well.
+fn custom_modes(keymaps: &HashMap<(String, String), String>) -> Vec<&str> {
+    keymaps
+        .values()
+        .flat_map(|action| action.split(';'))
+        .filter_map(|cmd| cmd.trim().strip_prefix("mode "))
+        .map(str::trim)
+        .collect()
+}
+
+/// Problems with keymaps that would otherwise only show when they're used:
+/// unknown modes and commands, and, without `timeout`, binds shadowed by a
+/// shorter one.
+pub fn check_keymaps(keymaps: &HashMap<(String, String), String>, timeout: bool) -> Vec<String> {
+    let custom_modes = custom_modes(keymaps);
+    let mut res = vec![];
+    for ((mode, keys), action) in keymaps {
+        let bind = format!("{mode} {}", notation::pretty(keys));
+        let is_frame_mode = frame::MODES.contains(&mode.as_str());
+        if !is_frame_mode
+            && !mode::NAMES.contains(&mode.as_str())
+            && !custom_modes.contains(&mode.as_str())
+        {
+            res.push(format!("{bind}: unknown mode"));
+        }
+        if is_frame_mode && keys.chars().count() > 1 {
+            res.push(format!("{bind}: {mode} binds are a single key"));
+        }
+        for cmd in action.split(';') {
+            let name = cmd
+                .trim()
+                .trim_start_matches(|c: char| c.is_ascii_digit())
+                .split(' ')
+                .next()
+                .unwrap_or_default();
+            if !is_command(name) {
+                res.push(format!("{bind}: unknown command {name:?}"));
+            }
+        }
+        if timeout {
+            continue;
+        }
+        for (other_mode, other) in keymaps.keys() {
+            if other_mode == mode && other.len() > keys.len() && other.starts_with(keys.as_str()) {
+                res.push(format!("{bind} shadows {}", notation::pretty(other)));
+            }
+        }
+    }
+    res.sort();
+    res
+}
+
+fn parse_config_registers(
+    registers: &HashMap<String, String>,
+    problems: &mut Vec<String>,
+) -> HashMap<char, Vec<Key>> {
+    let mut res = HashMap::new();
+    for (name, keys) in registers {
+        let mut chars = name.chars();
+        let c = match (chars.next(), chars.next()) {
+            (Some(c), None) if Registers::is_valid_name(c) => c.to_ascii_lowercase(),
+            _ => {
+                problems.push(format!("registers: Invalid register name: {name}"));
+                continue;
+            }
+        };
+        match notation::parse(keys, "") {
+            Ok(keys) => _ = res.insert(c, keys_from_str(&keys)),
+            Err(e) => problems.push(format!("registers.{name}: {e}")),
+        }
+    }
+    res
 }
 
 #[test]
@@ -257,9 +493,41 @@ fn parse_config_keymaps_works() {
     let mut keymaps = HashMap::new();
     keymaps.insert("banana,insert,philza;gr,ah".to_string(), "k".to_string());
     keymaps.insert("pizda;pilgrim".to_string(), "e".to_string());
-    let new_keymaps = parse_config_keymaps(&keymaps).unwrap();
+    keymaps.insert("frame;<C-t>".to_string(), "tabnew".to_string());
+    keymaps.insert("n;;".to_string(), "move right".to_string());
+    keymaps.insert("n;<leader>w".to_string(), "write".to_string());
+    keymaps.insert("nope".to_string(), "write".to_string());
+    let mut problems = vec![];
+    let new_keymaps = parse_config_keymaps(&keymaps, " ", &mut problems);
+    assert_eq!(problems, ["keymaps: No mode/keybind specified: nope"]);
     let mut expected = HashMap::new();
     new_keymap!(expected, "banana", "insert", "philza"; "gr,ah" => "k");
     new_keymap!(expected, "pizda"; "pilgrim" => "e");
+    new_keymap!(expected, "frame"; "\u{14}" => "tabnew");
+    new_keymap!(expected, "n"; ";" => "move right");
+    new_keymap!(expected, "n"; " w" => "write");
     assert_eq!(new_keymaps, expected);
 }
+
+#[test]
+fn check_keymaps_works() {
+    assert_eq!(
+        check_keymaps(&default_keymaps(), false),
+        Vec::<String>::new()
+    );
+    let mut keymaps = HashMap::new();
+    new_keymap!(keymaps, "normal"; "g" => "mode go");
+    new_keymap!(keymaps, "normal"; "gx" => "undo; banana 3");
+    new_keymap!(keymaps, "pilgrim"; "x" => "undo");
+    new_keymap!(keymaps, "frame"; "ab" => "tabnew");
+    assert_eq!(
+        check_keymaps(&keymaps, false),
+        [
+            "frame ab: frame binds are a single key",
+            "normal g shadows gx",
+            "normal gx: unknown command \"banana\"",
+            "pilgrim x: unknown mode",
+        ]
+    );
+    assert_eq!(check_keymaps(&keymaps, true).len(), 3);
+}
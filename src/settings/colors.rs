@@ -1,5 +1,15 @@
 use macroquad::prelude::*;
 
+use crate::settings::config;
+
+macro_rules! assign_if_some {
+    ($target:expr, $opt:expr) => {
+        if let Some(val) = $opt {
+            $target = val.0;
+        }
+    };
+}
+
 pub struct Colors {
     pub square_color: Color,
     pub bg_color: Color,
@@ -23,6 +33,8 @@ pub struct Colors {
 
     pub highlight_color: Color,
     pub visual_highlight_color: Color,
+    pub search_color: Color,
+    pub cursor_color: Color,
 
     pub invalid_color: Color,
 }
@@ -67,13 +79,57 @@ impl Default for Colors {
                 b: 0.7,
                 a: 1.0,
             },
+            search_color: Color {
+                r: 0.9,
+                g: 0.8,
+                b: 0.2,
+                a: 0.6,
+            },
+            cursor_color: Color {
+                r: 0.8,
+                g: 0.5,
+                b: 0.2,
+                a: 1.0,
+            },
 
             invalid_color: Color {
                 r: 1.0,
                 g: 0.0,
                 b: 0.0,
-                a: 0.7,
+                a: 0.8,
             },
         }
     }
 }
+
+impl Colors {
+    /// Overrides the colors a config sets.
+    pub fn apply(&mut self, colors: &config::Colors) {
+        assign_if_some!(self.square_color, colors.square_color);
+        assign_if_some!(self.bg_color, colors.bg_color);
+        assign_if_some!(self.outer_color, colors.outer_line);
+        assign_if_some!(self.box_color, colors.box_line);
+        assign_if_some!(self.normal_color, colors.normal_line);
+        assign_if_some!(self.window_gaps, colors.window_gaps);
+        assign_if_some!(self.selected_window, colors.selected_window);
+        assign_if_some!(self.selected_tab, colors.selected_tab);
+        assign_if_some!(self.selected_tab_font, colors.selected_tab_font);
+        assign_if_some!(self.inactive_tab_font, colors.inactive_tab_font);
+        assign_if_some!(self.inactive_tab_color, colors.inactive_tab_color);
+
+        assign_if_some!(self.normal_font, colors.normal_font_color);
+        assign_if_some!(self.note_font, colors.note_font_color);
+        assign_if_some!(self.cmd_font, colors.cmd_font_color);
+        assign_if_some!(self.status_font, colors.status_font_color);
+
+        assign_if_some!(self.cmd_bg, colors.cmd_bg_color);
+        assign_if_some!(self.status_bg, colors.status_bg_color);
+
+        assign_if_some!(self.highlight_color, colors.highlight_color);
+        assign_if_some!(self.visual_highlight_color, colors.visual_highlight_color);
+        assign_if_some!(self.search_color, colors.search_color);
+        assign_if_some!(self.cursor_color, colors.cursor_color);
+
+        assign_if_some!(self.invalid_color, colors.invalid_color);
+    }
+}
@@ -0,0 +1,71 @@
+//! Named sets of colors, built in or the user's in the themes directory.
+
+use std::collections::BTreeSet;
+
+use crate::settings::{
+    colors::Colors,
+    config::{self, themes_dir},
+};
+
+const BUILTIN: &[(&str, &str)] = &[
+    ("light", include_str!("../../assets/themes/light.toml")),
+    ("dark", include_str!("../../assets/themes/dark.toml")),
+    (
+        "high-contrast",
+        include_str!("../../assets/themes/high-contrast.toml"),
+    ),
+    (
+        "colorblind",
+        include_str!("../../assets/themes/colorblind.toml"),
+    ),
+];
+
+/// Every colorscheme `load` can find.
+pub fn names() -> Vec<String> {
+    let user = std::fs::read_dir(themes_dir())
+        .into_iter()
+        .flatten()
+        .filter_map(|x| {
+            let path = x.ok()?.path();
+            let stem = path.file_stem()?.to_str()?;
+            path.extension()
+                .is_some_and(|x| x == "toml")
+                .then(|| stem.to_string())
+        });
+    let builtin = BUILTIN.iter().map(|x| x.0.to_string());
+    builtin
+        .chain(user)
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+/// The colors of the colorscheme `name`, with the defaults for those it
+/// leaves out. A theme file takes precedence over a built-in scheme by the
+/// same name.
+pub fn load(name: &str) -> Result<(Colors, Vec<String>), String> {
+    let file = themes_dir().join(format!("{name}.toml"));
+    let (config, warnings) = if file.exists() {
+        config::load(&file)?
+    } else {
+        let (_, content) = BUILTIN
+            .iter()
+            .find(|x| x.0 == name)
+            .ok_or_else(|| format!("Unknown colorscheme: {name}"))?;
+        config::parse(content, name)?
+    };
+    let mut colors = Colors::default();
+    if let Some(config_colors) = &config.colors {
+        colors.apply(config_colors);
+    }
+    Ok((colors, warnings))
+}
+
+#[test]
+fn builtin_schemes_load() {
+    for (name, _) in BUILTIN {
+        let (_, warnings) = load(name).unwrap();
+        assert!(warnings.is_empty(), "{name}: {warnings:?}");
+    }
+    assert!(load("banana").is_err());
+}
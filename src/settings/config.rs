@@ -1,44 +1,102 @@
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+};
 
-use serde::Deserialize;
+use directories::ProjectDirs;
+use macroquad::prelude::Color;
+use serde::{
+    Deserialize, Deserializer,
+    de::{DeserializeOwned, Error},
+};
+use toml::{Table, Value};
+use toml_edit::{DocumentMut, TableLike, TomlError};
+
+use crate::{
+    input::notation,
+    settings::{
+        Settings, colorscheme, default_keymaps,
+        options::{OPTIONS, parse_color},
+    },
+};
+
+pub fn config_file() -> PathBuf {
+    let prj_dirs = ProjectDirs::from("com", "Taice", "Sucocku").unwrap();
+    prj_dirs.config_dir().join("config.toml")
+}
 
 #[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Config {
+    /// Config files merged under this one, relative to it. Taken out of the
+    /// table by `load_table` already, it's here to be checked.
+    #[allow(dead_code)]
+    pub include: Option<Vec<String>>,
+    /// Applied before `colors`, which override it.
+    pub colorscheme: Option<String>,
     pub colors: Option<Colors>,
     pub lines: Option<Lines>,
     pub opts: Option<Opts>,
     pub keymaps: Option<HashMap<String, String>>,
+    pub registers: Option<HashMap<String, String>>,
 }
 
 #[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Colors {
-    pub square_color: Option<[f32; 4]>,
-    pub bg_color: Option<[f32; 4]>,
-    pub outer_line: Option<[f32; 4]>,
-    pub box_line: Option<[f32; 4]>,
-    pub normal_line: Option<[f32; 4]>,
-    pub window_gaps: Option<[f32; 4]>,
-    pub selected_window: Option<[f32; 4]>,
-    pub selected_tab: Option<[f32; 4]>,
-    pub selected_tab_font: Option<[f32; 4]>,
-    pub inactive_tab_font: Option<[f32; 4]>,
-    pub inactive_tab_color: Option<[f32; 4]>,
+    pub square_color: Option<ConfigColor>,
+    pub bg_color: Option<ConfigColor>,
+    pub outer_line: Option<ConfigColor>,
+    pub box_line: Option<ConfigColor>,
+    pub normal_line: Option<ConfigColor>,
+    pub window_gaps: Option<ConfigColor>,
+    pub selected_window: Option<ConfigColor>,
+    pub selected_tab: Option<ConfigColor>,
+    pub selected_tab_font: Option<ConfigColor>,
+    pub inactive_tab_font: Option<ConfigColor>,
+    pub inactive_tab_color: Option<ConfigColor>,
 
-    pub normal_font_color: Option<[f32; 4]>,
-    pub note_font_color: Option<[f32; 4]>,
-    pub cmd_font_color: Option<[f32; 4]>,
-    pub status_font_color: Option<[f32; 4]>,
+    pub normal_font_color: Option<ConfigColor>,
+    pub note_font_color: Option<ConfigColor>,
+    pub cmd_font_color: Option<ConfigColor>,
+    pub status_font_color: Option<ConfigColor>,
 
-    pub cmd_bg_color: Option<[f32; 4]>,
-    pub status_bg_color: Option<[f32; 4]>,
+    pub cmd_bg_color: Option<ConfigColor>,
+    pub status_bg_color: Option<ConfigColor>,
 
-    pub highlight_color: Option<[f32; 4]>,
-    pub visual_highlight_color: Option<[f32; 4]>,
+    pub highlight_color: Option<ConfigColor>,
+    pub visual_highlight_color: Option<ConfigColor>,
+    pub search_color: Option<ConfigColor>,
+    pub cursor_color: Option<ConfigColor>,
 
-    pub invalid_color: Option<[f32; 4]>,
+    pub invalid_color: Option<ConfigColor>,
+}
+
+/// A color in the config, `[r, g, b, a]` from 0 to 1 or `"#rrggbb[aa]"`.
+#[derive(Debug, Clone, Copy)]
+pub struct ConfigColor(pub Color);
+
+impl<'de> Deserialize<'de> for ConfigColor {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Rgba([f32; 4]),
+            Hex(String),
+        }
+        let invalid = || D::Error::custom("expected a color, \"#rrggbb\" or [r, g, b, a]");
+        match Repr::deserialize(deserializer).map_err(|_| invalid())? {
+            Repr::Rgba([r, g, b, a]) => Ok(Self(Color::new(r, g, b, a))),
+            Repr::Hex(hex) if hex.starts_with('#') => {
+                parse_color(&hex).map(Self).ok_or_else(invalid)
+            }
+            Repr::Hex(_) => Err(invalid()),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Lines {
     pub outer_line_width: Option<f32>,
     pub box_line_width: Option<f32>,
@@ -47,12 +105,14 @@ pub struct Lines {
 }
 
 #[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Opts {
     pub outer_gaps: Option<f32>,
     pub command_font_size: Option<u16>,
     pub tabline_font_size: Option<u16>,
     pub tabline_gap: Option<f32>,
     pub visual_highlight_size: Option<f32>,
+    pub note_size: Option<f32>,
 
     pub auto_candidate_elimination: Option<bool>,
     pub auto_fill_candidates: Option<bool>,
@@ -62,4 +122,568 @@ pub struct Opts {
     pub highlight_square_instead_of_note: Option<bool>,
 
     pub remove_invalid: Option<bool>,
+
+    pub wrap_around: Option<bool>,
+
+    pub leader: Option<String>,
+
+    pub persist_keymaps: Option<bool>,
+
+    pub timeoutlen: Option<u32>,
+
+    pub which_key: Option<bool>,
+    pub which_key_delay: Option<u32>,
+}
+
+/// User colorschemes, `{name}.toml` configs of which `colors` is used.
+pub fn themes_dir() -> PathBuf {
+    config_file().with_file_name("themes")
+}
+
+/// Commands run at startup, one per line.
+pub fn rc_file() -> PathBuf {
+    config_file().with_file_name("sucockurc")
+}
+
+/// How deep config files can include other files.
+const MAX_INCLUDE_DEPTH: u8 = 8;
+
+/// A config for everyone, below the user's.
+fn system_config_file() -> Option<PathBuf> {
+    if cfg!(windows) {
+        std::env::var_os("ProgramData").map(|x| PathBuf::from(x).join("Sucocku\\config.toml"))
+    } else {
+        Some(PathBuf::from("/etc/sucocku/config.toml"))
+    }
+}
+
+/// Every config file, later ones overriding earlier ones: the system-wide
+/// one, the user's and the working directory's `.sucocku.toml`.
+pub fn config_files() -> Vec<PathBuf> {
+    let mut res: Vec<_> = system_config_file().into_iter().collect();
+    res.push(config_file());
+    res.push(PathBuf::from(".sucocku.toml"));
+    res
+}
+
+/// Loads and merges the config files that exist, then `overrides`, each
+/// `table.key=value` like `opts.wrap_around=true`. None without any of them.
+/// Also returns every config file and include, to watch for changes.
+pub fn load_layers(overrides: &[String]) -> (Option<Config>, Vec<String>, Vec<PathBuf>) {
+    let mut warnings = vec![];
+    let mut files = vec![];
+    let config = layers_table(None, overrides, &mut warnings, &mut files).and_then(|table| {
+        into_config(table, "config")
+            .map_err(|e| warnings.push(e))
+            .ok()
+    });
+    (config, warnings, files)
+}
+
+/// The config layers with `file` merged over the config files, as `:source`
+/// loads it, so `overrides` still come last. Fails if `file` can't be read or
+/// isn't valid toml.
+pub fn load_over_layers(
+    file: &Path,
+    overrides: &[String],
+) -> Result<(Config, Vec<String>, Vec<PathBuf>), String> {
+    let mut warnings = vec![];
+    let mut files = vec![];
+    let table = load_table(file, 0, &mut warnings, &mut vec![])?;
+    let res = layers_table(Some(table), overrides, &mut warnings, &mut files).unwrap_or_default();
+    let config = into_config(res, &file.display().to_string())?;
+    Ok((config, warnings, files))
+}
+
+/// The merged tables of `load_layers`, with `sourced` between the config
+/// files and `overrides`.
+fn layers_table(
+    sourced: Option<Table>,
+    overrides: &[String],
+    warnings: &mut Vec<String>,
+    files: &mut Vec<PathBuf>,
+) -> Option<Table> {
+    let mut res: Option<Table> = None;
+    for file in config_files() {
+        files.push(file.clone());
+        if !file.exists() {
+            continue;
+        }
+        match load_table(&file, 0, warnings, files) {
+            Ok(table) => merge(res.get_or_insert_default(), table),
+            Err(e) => warnings.push(e),
+        }
+    }
+    if let Some(table) = sourced {
+        merge(res.get_or_insert_default(), table);
+    }
+    if !overrides.is_empty() {
+        let table = overrides_table(overrides, warnings);
+        merge(res.get_or_insert_default(), table);
+    }
+    res
+}
+
+/// Loads a config and what it includes, leaving out the keys that are
+/// unknown or have the wrong type and returning a warning for each. Only a
+/// file that can't be read or isn't valid toml fails.
+pub fn load(file: &Path) -> Result<(Config, Vec<String>), String> {
+    let mut warnings = vec![];
+    let table = load_table(file, 0, &mut warnings, &mut vec![])?;
+    Ok((into_config(table, &file.display().to_string())?, warnings))
+}
+
+/// A config file's checked table, merged over the files it includes, which
+/// are added to `files`.
+fn load_table(
+    file: &Path,
+    depth: u8,
+    warnings: &mut Vec<String>,
+    files: &mut Vec<PathBuf>,
+) -> Result<Table, String> {
+    let name = file.display().to_string();
+    let content = std::fs::read_to_string(file).map_err(|e| format!("{name}: {e}"))?;
+    let mut table = parse_table(&content, &name, warnings)?;
+    let Some(Value::Array(includes)) = table.remove("include") else {
+        return Ok(table);
+    };
+    let mut res = Table::new();
+    for include in includes.iter().filter_map(Value::as_str) {
+        if depth >= MAX_INCLUDE_DEPTH {
+            warnings.push(format!("{name}: includes nested too deep at {include}"));
+            continue;
+        }
+        let included = file.parent().unwrap_or(Path::new("")).join(include);
+        files.push(included.clone());
+        match load_table(&included, depth + 1, warnings, files) {
+            Ok(x) => merge(&mut res, x),
+            Err(e) => warnings.push(e),
+        }
+    }
+    merge(&mut res, table);
+    Ok(res)
+}
+
+/// Puts `layer` over `base`, merging tables and replacing everything else.
+fn merge(base: &mut Table, layer: Table) {
+    for (key, value) in layer {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(base)), Value::Table(layer)) => merge(base, layer),
+            (_, value) => _ = base.insert(key, value),
+        }
+    }
+}
+
+/// `--set` arguments as a config table. Values that aren't toml are taken as
+/// strings, so `opts.leader=<Space>` works.
+fn overrides_table(overrides: &[String], warnings: &mut Vec<String>) -> Table {
+    let mut res = Table::new();
+    for arg in overrides {
+        let Some((path, value)) = arg.split_once('=') else {
+            warnings.push(format!("--set {arg}: expected key=value"));
+            continue;
+        };
+        let value = toml::from_str::<Table>(&format!("value = {value}"))
+            .ok()
+            .and_then(|mut x| x.remove("value"))
+            .unwrap_or_else(|| value.into());
+        let entry = match path.split_once('.') {
+            Some((section, key)) => {
+                let inner = Table::from_iter([(key.to_string(), value)]);
+                Table::from_iter([(section.to_string(), inner.into())])
+            }
+            None => Table::from_iter([(path.to_string(), value)]),
+        };
+        merge(&mut res, entry);
+    }
+    check_table(&mut res, "", "--set", warnings);
+    res
+}
+
+pub fn parse(content: &str, name: &str) -> Result<(Config, Vec<String>), String> {
+    let mut warnings = vec![];
+    let table = parse_table(content, name, &mut warnings)?;
+    Ok((into_config(table, name)?, warnings))
+}
+
+fn into_config(table: Table, name: &str) -> Result<Config, String> {
+    Value::Table(table)
+        .try_into()
+        .map_err(|e: toml::de::Error| format!("{name}: {}", e.message()))
+}
+
+/// Parses a config's table and checks it, see `check_table`.
+fn parse_table(content: &str, name: &str, warnings: &mut Vec<String>) -> Result<Table, String> {
+    let mut table: Table = toml::from_str(content).map_err(|e| {
+        let (line, col) = line_col(content, e.span().map_or(0, |x| x.start));
+        format!("{name}:{line}:{col}: {}", e.message())
+    })?;
+    check_table(&mut table, content, name, warnings);
+    Ok(table)
+}
+
+/// Removes what a `Config` wouldn't take from `table`, with a warning
+/// pointing at its line in `content` for each.
+fn check_table(table: &mut Table, content: &str, name: &str, warnings: &mut Vec<String>) {
+    let mut warn = |section: Option<&str>, key: &str, msg: String| {
+        let path = section.map_or(key.to_string(), |x| format!("{x}.{key}"));
+        let msg = if msg.starts_with("unknown field") {
+            format!("unknown key {path}")
+        } else {
+            format!("{path}: {msg}")
+        };
+        warnings.push(match find_key(content, section, key) {
+            Some(line) => format!("{name}:{line}: {msg}"),
+            None => format!("{name}: {msg}"),
+        });
+    };
+    for (section, value) in table.iter_mut() {
+        let Value::Table(entries) = value else {
+            continue;
+        };
+        let section = Some(section.as_str());
+        match section {
+            Some("colors") => retain_valid::<Colors>(entries, section, &mut warn),
+            Some("lines") => retain_valid::<Lines>(entries, section, &mut warn),
+            Some("opts") => retain_valid::<Opts>(entries, section, &mut warn),
+            Some("keymaps" | "registers") => {
+                retain_valid::<HashMap<String, String>>(entries, section, &mut warn)
+            }
+            _ => {}
+        }
+    }
+    retain_valid::<Config>(table, None, &mut warn);
+}
+
+/// Removes the entries `T` doesn't accept on their own.
+fn retain_valid<T: DeserializeOwned>(
+    table: &mut Table,
+    section: Option<&str>,
+    warn: &mut impl FnMut(Option<&str>, &str, String),
+) {
+    table.retain(|key, value| {
+        let entry = Table::from_iter([(key.to_string(), value.clone())]);
+        match Value::Table(entry).try_into::<T>() {
+            Ok(_) => true,
+            Err(e) => {
+                warn(section, key, e.message().to_string());
+                false
+            }
+        }
+    });
+}
+
+/// The 1-based line and column of a byte offset.
+fn line_col(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset.min(content.len())];
+    let line = before.matches('\n').count() + 1;
+    let col = before
+        .rsplit('\n')
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .count()
+        + 1;
+    (line, col)
+}
+
+/// The line `key` is set on in `section`, or where the `[key]` table starts.
+fn find_key(content: &str, section: Option<&str>, key: &str) -> Option<usize> {
+    let mut curr = None;
+    for (i, line) in content.lines().map(str::trim).enumerate() {
+        if let Some(header) = line.strip_prefix('[') {
+            let header = header.split(']').next().unwrap_or_default().trim();
+            if section.is_none() && header == key {
+                return Some(i + 1);
+            }
+            curr = Some(header);
+            continue;
+        }
+        let quoted = format!("\"{key}\"");
+        let rest = line
+            .strip_prefix(quoted.as_str())
+            .or_else(|| line.strip_prefix(key));
+        if curr == section && rest.is_some_and(|x| x.trim_start().starts_with('=')) {
+            return Some(i + 1);
+        }
+    }
+    None
+}
+
+/// The settings as a complete config, each key commented with what it does.
+/// Colors the colorscheme already gives are left commented out, so changing
+/// the scheme still changes them.
+pub fn generate(settings: &Settings) -> String {
+    let mut res = String::from(
+        "# Every key is optional, leaving one out keeps its default.\n\
+         # Colors are \"#rrggbb\", \"#rrggbbaa\" or [r, g, b, a] from 0 to 1.\n",
+    );
+    let Settings {
+        lines,
+        colors,
+        opts,
+        keymaps,
+        registers,
+        colorscheme,
+        ..
+    } = settings;
+    res += &format!(
+        "\n# light, dark, high-contrast, colorblind or a file in the themes directory\n\
+         colorscheme = {}\n",
+        Value::from(colorscheme.as_str())
+    );
+    let scheme = colorscheme::load(colorscheme).map_or_else(|_| Default::default(), |x| x.0);
+    for section in ["colors", "lines", "opts"] {
+        res += &format!("\n[{section}]\n");
+        if section == "colors" {
+            res += "# Commented out ones come from the colorscheme.\n";
+        }
+        for option in OPTIONS.iter().filter(|x| x.section == section) {
+            let value = option.get(lines, colors, opts);
+            let from_scheme = section == "colors" && value == option.get(lines, &scheme, opts);
+            let comment = if from_scheme { "# " } else { "" };
+            res += &format!(
+                "# {}\n{comment}{} = {}\n",
+                option.doc,
+                option.key,
+                value.toml()
+            );
+        }
+    }
+    let registers: Table = registers
+        .iter()
+        .map(|(name, keys)| {
+            let keys: String = keys.iter().map(|x| x.encode()).collect();
+            (name.to_string(), notation::pretty(&keys).into())
+        })
+        .collect();
+    res += "\n# \"modes;keys\" = \"commands\", modes separated by , and commands by ;.\n\
+            # Added to the default keymaps, \"\" removes one.\n";
+    res += &Table::from_iter([("keymaps".to_string(), keymaps_table(keymaps).into())]).to_string();
+    res += "\n# Registers filled at startup, as keys.\n";
+    res += &Table::from_iter([("registers".to_string(), registers.into())]).to_string();
+    res
+}
+
+/// Writes `settings` as a config to `file`, unless it exists and `force`
+/// isn't set.
+pub fn write_generated(settings: &Settings, file: &Path, force: bool) -> Result<(), String> {
+    if !force && file.exists() {
+        return Err(format!("{} exists, add ! to overwrite", file.display()));
+    }
+    if let Some(dir) = file.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    std::fs::write(file, generate(settings)).map_err(|e| e.to_string())
+}
+
+/// Records a runtime map of `keys` in `modes` to `action`, or an unmap
+/// without it, in the config file's `[keymaps]`, leaving the rest of the file
+/// as it is. Entries for the same keys and modes are replaced, and an unmap
+/// only needs an `""` entry when the bind doesn't come from the file itself.
+pub fn save_keymap(
+    modes: &[&str],
+    keys: &str,
+    action: Option<&str>,
+    leader: &str,
+) -> Result<(), String> {
+    let file = config_file();
+    if let Some(dir) = file.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let content = std::fs::read_to_string(&file).unwrap_or_default();
+    let content = edit_keymaps(&content, modes, keys, action, leader)?;
+    std::fs::write(&file, content).map_err(|e| e.to_string())
+}
+
+/// `content` with the `[keymaps]` entries changed, see `save_keymap`.
+fn edit_keymaps(
+    content: &str,
+    modes: &[&str],
+    keys: &str,
+    action: Option<&str>,
+    leader: &str,
+) -> Result<String, String> {
+    let mut doc: DocumentMut = content.parse().map_err(|e: TomlError| e.to_string())?;
+    let table = doc
+        .entry("keymaps")
+        .or_insert(toml_edit::table())
+        .as_table_like_mut()
+        .ok_or("keymaps isn't a table")?;
+    let own = remove_keymap(table, modes, keys, leader);
+    let (modes, action) = match action {
+        Some(action) => (modes.to_vec(), action),
+        None => {
+            let defaults = default_keymaps();
+            let modes = modes
+                .iter()
+                .filter(|x| {
+                    !own.contains(x) || defaults.contains_key(&(x.to_string(), keys.to_string()))
+                })
+                .copied()
+                .collect();
+            (modes, "")
+        }
+    };
+    if !modes.is_empty() {
+        let key = format!("{};{}", modes.join(","), notation::pretty(keys));
+        table.insert(&key, toml_edit::value(action));
+    }
+    Ok(doc.to_string())
+}
+
+/// Takes `modes` out of the entries binding `keys`, dropping the entries
+/// left without modes. Returns the modes that had an entry.
+fn remove_keymap<'a>(
+    table: &mut dyn TableLike,
+    modes: &[&'a str],
+    keys: &str,
+    leader: &str,
+) -> Vec<&'a str> {
+    let mut res = vec![];
+    let names: Vec<_> = table.iter().map(|(k, _)| k.to_string()).collect();
+    for name in names {
+        let Some((entry_modes, bind)) = name.split_once(';') else {
+            continue;
+        };
+        if notation::parse(bind, leader).ok().as_deref() != Some(keys) {
+            continue;
+        }
+        let (removed, kept): (Vec<_>, Vec<_>) =
+            entry_modes.split(',').partition(|x| modes.contains(x));
+        if removed.is_empty() {
+            continue;
+        }
+        res.extend(modes.iter().filter(|x| removed.contains(x)));
+        let decor = table.key(&name).map(|x| x.leaf_decor().clone());
+        let item = table.remove(&name).unwrap();
+        if !kept.is_empty() {
+            let name = format!("{};{bind}", kept.join(","));
+            table.insert(&name, item);
+            if let (Some(mut key), Some(decor)) = (table.key_mut(&name), decor) {
+                *key.leaf_decor_mut() = decor;
+            }
+        }
+    }
+    res
+}
+
+/// The keymaps in the config's `modes;keys` form, with the modes sharing a
+/// bind grouped together.
+fn keymaps_table(keymaps: &HashMap<(String, String), String>) -> toml::Table {
+    let mut grouped: BTreeMap<(&str, &str), Vec<&str>> = BTreeMap::new();
+    for ((mode, keys), action) in keymaps {
+        grouped
+            .entry((keys.as_str(), action.as_str()))
+            .or_default()
+            .push(mode);
+    }
+    grouped
+        .into_iter()
+        .map(|((keys, action), mut modes)| {
+            modes.sort();
+            let key = format!("{};{}", modes.join(","), notation::pretty(keys));
+            (key, action.into())
+        })
+        .collect()
+}
+
+#[test]
+fn keymaps_table_works() {
+    let mut keymaps = HashMap::new();
+    for mode in ["normal", "note"] {
+        keymaps.insert((mode.to_string(), "h".to_string()), "move left".to_string());
+    }
+    keymaps.insert(
+        ("frame".to_string(), "\u{14}".to_string()),
+        "tabnew".to_string(),
+    );
+    let table = keymaps_table(&keymaps);
+    assert_eq!(table["normal,note;h"].as_str(), Some("move left"));
+    assert_eq!(table["frame;<C-t>"].as_str(), Some("tabnew"));
+    assert_eq!(table.len(), 2);
+}
+
+#[test]
+fn edit_keymaps_keeps_the_rest() {
+    let content = "# mine\n[opts]\nleader = \"<Space>\" # comment\n\n[keymaps]\n\
+                   # moves\n\"normal,note;<leader>h\" = \"move left\"\n";
+    let edited = edit_keymaps(content, &["normal"], "Q", Some("feed gg"), " ").unwrap();
+    assert_eq!(edited, format!("{content}\"normal;Q\" = \"feed gg\"\n"));
+    let edited = edit_keymaps(&edited, &["normal"], "Q", None, " ").unwrap();
+    assert_eq!(edited, content);
+    let edited = edit_keymaps(content, &["normal"], " h", None, " ").unwrap();
+    assert!(edited.ends_with("[keymaps]\n# moves\n\"note;<leader>h\" = \"move left\"\n"));
+    let edited = edit_keymaps("", &["normal"], "h", None, " ").unwrap();
+    assert_eq!(edited, "[keymaps]\n\"normal;h\" = \"\"\n");
+}
+
+#[test]
+fn parse_keeps_valid_parts() {
+    let content =
+        "bogus = 1\n[opts]\nwrap_around = \"yes\"\ncheck_input = true\n[lines]\nwidth = 2\n";
+    let (config, warnings) = parse(content, "test").unwrap();
+    assert_eq!(config.opts.unwrap().check_input, Some(true));
+    assert_eq!(
+        warnings,
+        [
+            "test:6: unknown key lines.width",
+            "test:3: opts.wrap_around: invalid type: string \"yes\", expected a boolean",
+            "test:1: unknown key bogus",
+        ]
+    );
+    assert_eq!(
+        parse("[opts", "test").unwrap_err(),
+        "test:1:6: unclosed table, expected `]`"
+    );
+}
+
+#[test]
+fn generate_round_trips() {
+    let (settings, _) = Settings::from_config(&None);
+    let content = generate(&settings);
+    assert!(content.contains("\n# cursor_color = "));
+    let (config, warnings) = parse(&content, "test").unwrap();
+    assert!(warnings.is_empty());
+    let (loaded, problems) = Settings::from_config(&Some(config));
+    assert!(problems.is_empty());
+    assert_eq!(generate(&loaded), content);
+}
+
+#[test]
+fn layers_merge() {
+    let dir = std::env::temp_dir().join(format!("sucocku-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let base = "[opts]\nwrap_around = true\ncheck_input = false\n[keymaps]\n\"normal;h\" = \"move left\"\n";
+    std::fs::write(dir.join("base.toml"), base).unwrap();
+    let own = "include = [\"base.toml\"]\n[opts]\ncheck_input = true\n[keymaps]\n\"normal;l\" = \"move right\"\n";
+    std::fs::write(dir.join("config.toml"), own).unwrap();
+    let (config, warnings) = load(&dir.join("config.toml")).unwrap();
+    let overrides = [
+        "opts.leader=<Space>".to_string(),
+        "opts.wrap_around=false".to_string(),
+    ];
+    let (sourced, _, _) = load_over_layers(&dir.join("config.toml"), &overrides).unwrap();
+    let mut files = vec![];
+    load_table(&dir.join("config.toml"), 0, &mut vec![], &mut files).unwrap();
+    assert_eq!(files, [dir.join("base.toml")]);
+    std::fs::remove_dir_all(&dir).unwrap();
+    let sourced = sourced.opts.unwrap();
+    assert_eq!(sourced.leader.as_deref(), Some("<Space>"));
+    assert_eq!(sourced.check_input, Some(true));
+    assert_eq!(sourced.wrap_around, Some(false));
+    assert!(warnings.is_empty());
+    let opts = config.opts.unwrap();
+    assert_eq!(
+        (opts.wrap_around, opts.check_input),
+        (Some(true), Some(true))
+    );
+    assert_eq!(config.keymaps.unwrap().len(), 2);
+
+    let mut warnings = vec![];
+    let overrides = ["opts.leader=<Space>", "opts.timeoutlen=300", "opts.nope=1"];
+    let table = overrides_table(&overrides.map(String::from), &mut warnings);
+    assert_eq!(table["opts"]["leader"].as_str(), Some("<Space>"));
+    assert_eq!(table["opts"]["timeoutlen"].as_integer(), Some(300));
+    assert_eq!(warnings, ["--set: unknown key opts.nope"]);
 }
@@ -0,0 +1,480 @@
+//! Every option `:set` knows about, and how to show and change it.
+
+use macroquad::prelude::Color;
+
+use crate::{
+    input::notation,
+    settings::{colors::Colors, lines::Lines, opts::Opts},
+};
+
+/// An option's value, borrowed from wherever it lives.
+pub enum Field<'a> {
+    Bool(&'a mut bool),
+    F32(&'a mut f32),
+    U16(&'a mut u16),
+    U32(&'a mut u32),
+    /// Keys, shown and set in key notation.
+    Keys(&'a mut String),
+    Color(&'a mut Color),
+}
+
+/// An option's value, copied out of wherever it lives.
+#[derive(Clone, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    F32(f32),
+    U16(u16),
+    U32(u32),
+    Keys(String),
+    Color(Color),
+}
+
+impl Value {
+    pub fn value(&self) -> String {
+        match self {
+            Value::Bool(x) => x.to_string(),
+            Value::F32(x) => x.to_string(),
+            Value::U16(x) => x.to_string(),
+            Value::U32(x) => x.to_string(),
+            Value::Keys(x) => notation::pretty(x),
+            Value::Color(x) => color_to_hex(*x),
+        }
+    }
+
+    /// The value as it's written in the config.
+    pub fn toml(&self) -> String {
+        match self {
+            Value::F32(x) => format!("{x:?}"),
+            Value::Keys(_) | Value::Color(_) => toml::Value::from(self.value()).to_string(),
+            _ => self.value(),
+        }
+    }
+}
+
+impl Field<'_> {
+    pub fn get(&self) -> Value {
+        match self {
+            Field::Bool(x) => Value::Bool(**x),
+            Field::F32(x) => Value::F32(**x),
+            Field::U16(x) => Value::U16(**x),
+            Field::U32(x) => Value::U32(**x),
+            Field::Keys(x) => Value::Keys(x.to_string()),
+            Field::Color(x) => Value::Color(**x),
+        }
+    }
+
+    pub fn value(&self) -> String {
+        self.get().value()
+    }
+
+    pub fn set(&mut self, value: &str) -> Result<(), String> {
+        let invalid = || format!("Invalid value: {value}");
+        match self {
+            Field::Bool(x) => **x = value.parse().map_err(|_| invalid())?,
+            Field::F32(x) => **x = value.parse().map_err(|_| invalid())?,
+            Field::U16(x) => **x = value.parse().map_err(|_| invalid())?,
+            Field::U32(x) => **x = value.parse().map_err(|_| invalid())?,
+            Field::Keys(x) => **x = notation::parse(value, "")?,
+            Field::Color(x) => **x = parse_color(value).ok_or_else(invalid)?,
+        }
+        Ok(())
+    }
+
+    fn copy_from(&mut self, other: Field) {
+        match (self, other) {
+            (Field::Bool(x), Field::Bool(y)) => **x = *y,
+            (Field::F32(x), Field::F32(y)) => **x = *y,
+            (Field::U16(x), Field::U16(y)) => **x = *y,
+            (Field::U32(x), Field::U32(y)) => **x = *y,
+            (Field::Keys(x), Field::Keys(y)) => **x = y.clone(),
+            (Field::Color(x), Field::Color(y)) => **x = *y,
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Where an option can be overridden with `:setlocal`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    Global,
+    Buffer,
+    Window,
+}
+
+pub struct OptionDef {
+    pub name: &'static str,
+    pub scope: Scope,
+    /// Where the option goes in the config: its table and key.
+    pub section: &'static str,
+    pub key: &'static str,
+    pub doc: &'static str,
+    field: for<'a> fn(&'a mut Lines, &'a mut Colors, &'a mut Opts) -> Field<'a>,
+    get: fn(&Lines, &Colors, &Opts) -> Value,
+}
+
+impl OptionDef {
+    pub fn field<'a>(
+        &self,
+        lines: &'a mut Lines,
+        colors: &'a mut Colors,
+        opts: &'a mut Opts,
+    ) -> Field<'a> {
+        (self.field)(lines, colors, opts)
+    }
+
+    /// The option's value, without borrowing anything mutably.
+    pub fn get(&self, lines: &Lines, colors: &Colors, opts: &Opts) -> Value {
+        (self.get)(lines, colors, opts)
+    }
+
+    /// The value the option has without a config.
+    pub fn default_value(&self) -> String {
+        let (mut lines, mut colors, mut opts) = defaults();
+        self.field(&mut lines, &mut colors, &mut opts).value()
+    }
+
+    /// Values worth completing after `opt=`.
+    pub fn values(&self) -> Vec<String> {
+        let (mut lines, mut colors, mut opts) = defaults();
+        match self.field(&mut lines, &mut colors, &mut opts) {
+            Field::Bool(_) => vec!["true".to_string(), "false".to_string()],
+            field => vec![field.value()],
+        }
+    }
+}
+
+macro_rules! option {
+    ($name:expr, lines.$field:ident, $kind:ident; $key:ident, $doc:expr) => {
+        OptionDef {
+            name: $name,
+            scope: Scope::Global,
+            section: "lines",
+            key: stringify!($key),
+            doc: $doc,
+            field: |lines, _, _| Field::$kind(&mut lines.$field),
+            get: |lines, _, _| Value::$kind(lines.$field.to_owned()),
+        }
+    };
+    ($name:expr, colors.$field:ident; $key:ident, $doc:expr) => {
+        OptionDef {
+            name: $name,
+            scope: Scope::Global,
+            section: "colors",
+            key: stringify!($key),
+            doc: $doc,
+            field: |_, colors, _| Field::Color(&mut colors.$field),
+            get: |_, colors, _| Value::Color(colors.$field),
+        }
+    };
+    ($name:expr, opts.$field:ident, $kind:ident $(, $scope:ident)?; $key:ident, $doc:expr) => {
+        OptionDef {
+            name: $name,
+            scope: option!(@scope $($scope)?),
+            section: "opts",
+            key: stringify!($key),
+            doc: $doc,
+            field: |_, _, opts| Field::$kind(&mut opts.$field),
+            get: |_, _, opts| Value::$kind(opts.$field.to_owned()),
+        }
+    };
+    (@scope) => { Scope::Global };
+    (@scope $scope:ident) => { Scope::$scope };
+}
+
+#[rustfmt::skip]
+pub const OPTIONS: &[OptionDef] = &[
+    option!("candidate-elimination", opts.auto_candidate_elimination, Bool, Buffer;
+        auto_candidate_elimination, "Remove a number from the notes it rules out when it's entered"),
+    option!("auto-fill-candidates", opts.auto_fill_candidates, Bool, Buffer;
+        auto_fill_candidates, "Fill empty squares' notes with every possible number"),
+    option!("check-input", opts.check_input, Bool, Buffer;
+        check_input, "Check entered numbers against the solution"),
+    option!("remove-invalid", opts.remove_invalid, Bool, Buffer;
+        remove_invalid, "Refuse wrong numbers instead of marking them"),
+    option!("highlight-square", opts.highlight_square_instead_of_note, Bool, Window;
+        highlight_square_instead_of_note, "Highlight whole squares instead of the matching notes"),
+    option!("wrap", opts.wrap_around, Bool;
+        wrap_around, "Moving past an edge of the grid comes back on the other side"),
+    option!("outer-gaps", opts.outer_gaps, F32;
+        outer_gaps, "Space around the edges of the window"),
+    option!("command-font-size", opts.command_font_size, U16;
+        command_font_size, "Font size of the command line and status bar"),
+    option!("tabline-font-size", opts.tabline_font_size, U16;
+        tabline_font_size, "Font size of the tabline"),
+    option!("tabline-gap", opts.tabline_gap, F32;
+        tabline_gap, "Space above and below the tabline"),
+    option!("visual-highlight-size", opts.visual_highlight_size, F32, Window;
+        visual_highlight_size, "Size of the visual selection's highlight, relative to a square"),
+    option!("note-size", opts.note_size, F32, Window;
+        note_size, "Scale of the notes' font"),
+    option!("leader", opts.leader, Keys;
+        leader, "What <leader> stands for in keymaps, in key notation"),
+    option!("persist-maps", opts.persist_keymaps, Bool;
+        persist_keymaps, "Write :map and :unmap changes to this file"),
+    option!("timeoutlen", opts.timeoutlen, U32;
+        timeoutlen, "Milliseconds to wait for a longer keymap, 0 to not wait"),
+    option!("which-key", opts.which_key, Bool;
+        which_key, "Show what pending keys can still become"),
+    option!("which-key-delay", opts.which_key_delay, U32;
+        which_key_delay, "Milliseconds before the which-key popup shows"),
+
+    option!("outer-line-width", lines.outer_width, F32;
+        outer_line_width, "Width of the grid's border"),
+    option!("box-line-width", lines.box_width, F32;
+        box_line_width, "Width of the lines between boxes"),
+    option!("normal-line-width", lines.normal_width, F32;
+        normal_line_width, "Width of the lines between squares"),
+    option!("window-gaps", lines.window_gaps, F32;
+        window_gaps, "Space between windows"),
+
+    option!("square-color", colors.square_color;
+        square_color, "Background of the squares"),
+    option!("bg-color", colors.bg_color;
+        bg_color, "Background of the window"),
+    option!("outer-line", colors.outer_color;
+        outer_line, "The grid's border"),
+    option!("box-line", colors.box_color;
+        box_line, "Lines between boxes"),
+    option!("normal-line", colors.normal_color;
+        normal_line, "Lines between squares"),
+    option!("window-gaps-color", colors.window_gaps;
+        window_gaps, "Space between windows"),
+    option!("selected-window", colors.selected_window;
+        selected_window, "Outline of the selected window"),
+    option!("selected-tab", colors.selected_tab;
+        selected_tab, "Background of the selected tab"),
+    option!("selected-tab-font", colors.selected_tab_font;
+        selected_tab_font, "Text of the selected tab"),
+    option!("inactive-tab-font", colors.inactive_tab_font;
+        inactive_tab_font, "Text of the other tabs"),
+    option!("inactive-tab-color", colors.inactive_tab_color;
+        inactive_tab_color, "Background of the other tabs"),
+    option!("normal-font-color", colors.normal_font;
+        normal_font_color, "Numbers in the grid"),
+    option!("note-font-color", colors.note_font;
+        note_font_color, "Notes in the grid"),
+    option!("cmd-font-color", colors.cmd_font;
+        cmd_font_color, "Text of the command line"),
+    option!("status-font-color", colors.status_font;
+        status_font_color, "Text of the status bar"),
+    option!("cmd-bg-color", colors.cmd_bg;
+        cmd_bg_color, "Background of the command line"),
+    option!("status-bg-color", colors.status_bg;
+        status_bg_color, "Background of the status bar"),
+    option!("highlight-color", colors.highlight_color;
+        highlight_color, "Highlighted squares and notes"),
+    option!("visual-highlight-color", colors.visual_highlight_color;
+        visual_highlight_color, "The visual selection"),
+    option!("search-color", colors.search_color;
+        search_color, "Search matches"),
+    option!("cursor-color", colors.cursor_color;
+        cursor_color, "Extra cursors"),
+    option!("invalid-color", colors.invalid_color;
+        invalid_color, "Wrong numbers"),
+];
+
+pub fn find(name: &str) -> Option<&'static OptionDef> {
+    OPTIONS.iter().find(|x| x.name == name)
+}
+
+fn defaults() -> (Lines, Colors, Opts) {
+    (Lines::default(), Colors::default(), Opts::default())
+}
+
+/// What a `:set` argument asks of its option.
+pub enum Action<'a> {
+    /// `opt=value`
+    Assign(&'a str),
+    /// `opt?`
+    Show,
+    /// `opt&`, back to the default.
+    Reset,
+    /// `opt<`, back to the global value for `:setlocal`.
+    Inherit,
+    /// `opt`, setting booleans and showing the rest.
+    Bare,
+    /// `noopt`, or `opt!` and `invopt` to toggle.
+    Bool(Option<bool>),
+}
+
+pub fn parse_arg(arg: &str) -> Result<(&'static OptionDef, Action<'_>), String> {
+    let lookup = |name: &str| find(name).ok_or_else(|| format!("Unknown option: {name}"));
+    let (name, action) = if let Some((name, value)) = arg.split_once('=') {
+        (name, Action::Assign(value))
+    } else if let Some(name) = arg.strip_suffix('?') {
+        (name, Action::Show)
+    } else if let Some(name) = arg.strip_suffix('&') {
+        (name, Action::Reset)
+    } else if let Some(name) = arg.strip_suffix('<') {
+        (name, Action::Inherit)
+    } else if find(arg).is_some() {
+        (arg, Action::Bare)
+    } else if let Some(name) = arg.strip_suffix('!').or(arg.strip_prefix("inv")) {
+        (name, Action::Bool(None))
+    } else if let Some(name) = arg.strip_prefix("no") {
+        (name, Action::Bool(Some(false)))
+    } else {
+        return Err(format!("Unknown option: {arg}"));
+    };
+    Ok((lookup(name)?, action))
+}
+
+/// Does what `action` asks to `field`, returning what to show, if anything.
+fn apply(option: &OptionDef, action: Action, field: &mut Field) -> Result<Option<String>, String> {
+    let show = |field: &Field| Ok(Some(format!("{}={}", option.name, field.value())));
+    match (action, &mut *field) {
+        (Action::Assign(value), _) => field.set(value)?,
+        (Action::Show, _) => return show(field),
+        (Action::Reset, _) => {
+            let (mut lines, mut colors, mut opts) = defaults();
+            field.copy_from(option.field(&mut lines, &mut colors, &mut opts));
+        }
+        (Action::Inherit, _) => return Err(format!("Only for :setlocal: {}<", option.name)),
+        (Action::Bare, Field::Bool(x)) => **x = true,
+        (Action::Bare, _) => return show(field),
+        (Action::Bool(value), Field::Bool(x)) => **x = value.unwrap_or(!**x),
+        (Action::Bool(_), _) => return Err(format!("Not a boolean option: {}", option.name)),
+    }
+    Ok(None)
+}
+
+/// Applies one `:set` argument: `opt=value`, `opt?` to show it, `opt&` to
+/// reset it, and for booleans `opt`, `noopt`, `opt!` and `invopt`. Returns
+/// what to show, if anything.
+pub fn set(
+    arg: &str,
+    lines: &mut Lines,
+    colors: &mut Colors,
+    opts: &mut Opts,
+) -> Result<Option<String>, String> {
+    let (option, action) = parse_arg(arg)?;
+    apply(option, action, &mut option.field(lines, colors, opts))
+}
+
+/// Options set with `:setlocal`, overriding the global ones of a buffer or
+/// window.
+#[derive(Default, Clone, Debug)]
+pub struct LocalOpts(Vec<(&'static str, String)>);
+
+impl LocalOpts {
+    /// Applies a `:setlocal` argument, `opts` being what the options resolve
+    /// to so far.
+    pub fn set(&mut self, arg: &str, opts: &Opts) -> Result<Option<String>, String> {
+        let (option, action) = parse_arg(arg)?;
+        if let Action::Inherit = action {
+            self.0.retain(|x| x.0 != option.name);
+            return Ok(None);
+        }
+        let (mut lines, mut colors, mut opts) = (Lines::default(), Colors::default(), opts.clone());
+        let mut field = option.field(&mut lines, &mut colors, &mut opts);
+        let msg = apply(option, action, &mut field)?;
+        // only a change replaces the old value, showing it keeps it
+        if msg.is_none() {
+            self.0.retain(|x| x.0 != option.name);
+            self.0.push((option.name, field.value()));
+        }
+        Ok(msg)
+    }
+
+    /// Overrides `opts` with the local values.
+    pub fn apply(&self, opts: &mut Opts) {
+        let (mut lines, mut colors) = (Lines::default(), Colors::default());
+        for (name, value) in &self.0 {
+            if let Some(option) = find(name) {
+                let _ = option.field(&mut lines, &mut colors, opts).set(value);
+            }
+        }
+    }
+
+    pub fn list(&self) -> impl Iterator<Item = String> {
+        self.0.iter().map(|(name, value)| format!("{name}={value}"))
+    }
+}
+
+/// `name=value` for every option, or only those changed from their defaults.
+pub fn list(lines: &Lines, colors: &Colors, opts: &Opts, all: bool) -> Vec<String> {
+    OPTIONS
+        .iter()
+        .filter_map(|option| {
+            let value = option.get(lines, colors, opts).value();
+            (all || value != option.default_value()).then(|| format!("{}={value}", option.name))
+        })
+        .collect()
+}
+
+/// Parses `#rrggbb`, `#rrggbbaa` or `r,g,b,a` with components from 0 to 1.
+pub fn parse_color(str: &str) -> Option<Color> {
+    if let Some(hex) = str.strip_prefix('#') {
+        if !matches!(hex.len(), 6 | 8) || !hex.is_ascii() {
+            return None;
+        }
+        let byte = |i: usize| {
+            let x = hex.get(i * 2..i * 2 + 2).unwrap_or("ff");
+            u8::from_str_radix(x, 16).ok()
+        };
+        return Some(Color::from_rgba(byte(0)?, byte(1)?, byte(2)?, byte(3)?));
+    }
+    let parts = str
+        .split(',')
+        .map(|x| x.trim().parse().ok())
+        .collect::<Option<Vec<f32>>>()?;
+    match parts[..] {
+        [r, g, b, a] => Some(Color::new(r, g, b, a)),
+        _ => None,
+    }
+}
+
+pub fn color_to_hex(color: Color) -> String {
+    let [r, g, b, a]: [u8; 4] = color.into();
+    format!("#{r:02x}{g:02x}{b:02x}{a:02x}")
+}
+
+#[test]
+fn set_works() {
+    let (mut lines, mut colors, mut opts) = defaults();
+    let mut set = |arg: &str| set(arg, &mut lines, &mut colors, &mut opts);
+    assert_eq!(set("wrap?"), Ok(Some("wrap=false".to_string())));
+    assert_eq!(set("wrap"), Ok(None));
+    assert_eq!(set("wrap!"), Ok(None));
+    assert_eq!(set("invwrap"), Ok(None));
+    assert_eq!(set("nowrap"), Ok(None));
+    assert_eq!(set("wrap?"), Ok(Some("wrap=false".to_string())));
+    assert_eq!(set("outer-gaps=8.5"), Ok(None));
+    assert_eq!(set("outer-gaps"), Ok(Some("outer-gaps=8.5".to_string())));
+    assert!(set("outer-gaps=far").is_err());
+    assert!(set("noouter-gaps").is_err());
+    assert!(set("banana").is_err());
+    assert_eq!(set("cursor-color=#ff000080"), Ok(None));
+    assert_eq!(
+        set("cursor-color?"),
+        Ok(Some("cursor-color=#ff000080".to_string()))
+    );
+    assert_eq!(set("leader=<Space>"), Ok(None));
+    assert_eq!(set("outer-gaps&"), Ok(None));
+    assert_eq!(
+        list(&lines, &colors, &opts, false),
+        ["leader=<Space>", "cursor-color=#ff000080"]
+    );
+
+    let mut local = LocalOpts::default();
+    assert_eq!(local.set("check-input!", &opts), Ok(None));
+    assert_eq!(local.set("note-size=1.5", &opts), Ok(None));
+    assert_eq!(local.set("check-input<", &opts), Ok(None));
+    local.apply(&mut opts);
+    assert_eq!((opts.check_input, opts.note_size), (true, 1.5));
+    assert_eq!(
+        local.set("note-size?", &opts),
+        Ok(Some("note-size=1.5".to_string()))
+    );
+    assert_eq!(local.list().collect::<Vec<_>>(), ["note-size=1.5"]);
+    assert_eq!(
+        local.set("note-size", &opts),
+        Ok(Some("note-size=1.5".to_string()))
+    );
+    assert!(local.set("note-size=abc", &opts).is_err());
+    assert!(local.set("nonote-size", &opts).is_err());
+    assert_eq!(local.list().collect::<Vec<_>>(), ["note-size=1.5"]);
+
+    assert_eq!(parse_color("1,0,0.5,1"), Some(Color::new(1., 0., 0.5, 1.)));
+    assert_eq!(parse_color("#12345"), None);
+}
@@ -1,5 +1,8 @@
-use crate::settings::{BASE_COMMAND_FONT_SIZE, BASE_TABLINE_FONT_SIZE};
+use crate::settings::{
+    BASE_BOX_SIZE, BASE_COMMAND_FONT_SIZE, BASE_NOTE_FONT_SIZE, BASE_TABLINE_FONT_SIZE,
+};
 
+#[derive(Clone)]
 pub struct Opts {
     pub remove_invalid: bool,
 
@@ -8,6 +11,8 @@ pub struct Opts {
     pub tabline_font_size: u16,
     pub tabline_gap: f32,
     pub visual_highlight_size: f32,
+    /// Scale of the note font.
+    pub note_size: f32,
 
     pub auto_candidate_elimination: bool,
     pub auto_fill_candidates: bool,
@@ -15,6 +20,23 @@ pub struct Opts {
     pub check_input: bool,
 
     pub highlight_square_instead_of_note: bool,
+
+    pub wrap_around: bool,
+
+    /// What `<leader>` stands for in keymaps, already parsed.
+    pub leader: String,
+
+    /// Whether `:map` and friends write the keymaps back to the config file.
+    pub persist_keymaps: bool,
+
+    /// Milliseconds to wait for more keys when what was typed is a bind as well
+    /// as the start of a longer one, 0 to never wait.
+    pub timeoutlen: u32,
+
+    /// Whether to list what pending keys can still become, after
+    /// `which_key_delay` milliseconds.
+    pub which_key: bool,
+    pub which_key_delay: u32,
 }
 
 impl Default for Opts {
@@ -25,6 +47,7 @@ impl Default for Opts {
             tabline_font_size: BASE_TABLINE_FONT_SIZE,
             tabline_gap: 4.0,
             visual_highlight_size: 3.0,
+            note_size: 1.0,
 
             auto_candidate_elimination: false,
             auto_fill_candidates: false,
@@ -33,6 +56,26 @@ impl Default for Opts {
             remove_invalid: false,
 
             highlight_square_instead_of_note: false,
+
+            wrap_around: false,
+
+            leader: "\\".to_string(),
+
+            persist_keymaps: false,
+
+            timeoutlen: 0,
+
+            which_key: true,
+            which_key_delay: 500,
         }
     }
 }
+
+impl Opts {
+    pub fn get_highlight_size(&self, box_size: f32) -> f32 {
+        self.visual_highlight_size * (box_size / BASE_BOX_SIZE)
+    }
+    pub fn get_note_font_size(&self, box_size: f32) -> u16 {
+        (BASE_NOTE_FONT_SIZE as f32 * self.note_size * (box_size / BASE_BOX_SIZE)) as u16
+    }
+}
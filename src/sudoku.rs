@@ -1,16 +1,34 @@
+mod cmd_line;
 mod history;
-mod mode;
+mod jumps;
+pub mod mode;
+mod motion;
+mod query;
+mod selection;
 mod sudoku_board;
 
+use cmd_line::CmdLine;
 use history::History;
+use jumps::JumpList;
 
 use crate::{
+    cmd_history::CmdHistory,
     draw_rect_outlines,
-    frame::center_text,
-    settings::{FONT_SCALE, Settings},
+    frame::{self, center_text, draw_popup},
+    input::{CTRL, Key, SHIFT, ctrl, keys_from_str, notation},
+    messages::Messages,
+    registers::{Register, Registers, UNNAMED},
+    settings::{
+        FONT_SCALE, Settings, check_keymaps, colorscheme, config, is_mode,
+        options::{self, LocalOpts, Scope},
+        opts::Opts,
+    },
     sudoku::{
         history::Change,
-        mode::Mode,
+        mode::{Mode, Operator, Pending, VisualKind},
+        motion::{Motion, path, scan},
+        query::Query,
+        selection::Selection,
         sudoku_board::{BacktrackResult, SudokuBoard},
     },
     unwrap_or_else,
@@ -18,59 +36,101 @@ use crate::{
 
 use arboard::Clipboard;
 use macroquad::prelude::*;
-use std::{cell::RefCell, collections::HashSet, f32, rc::Rc};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    f32,
+    path::PathBuf,
+    rc::Rc,
+};
 
 const NOTE_FLAG: u16 = 15;
 const ALL_NOTES: u16 = 0b1000000111111111;
-
-#[derive(Default)]
-struct Selection([u16; 9]);
-
-impl Selection {
-    fn toggle(&mut self, y: impl Into<usize>, x: impl Into<usize>) {
-        self.0[y.into()] ^= 1 << x.into();
-    }
-
-    fn get(&self, y: impl Into<usize>, x: impl Into<usize>) -> bool {
-        self.0[y.into()] & (1 << x.into()) > 0
-    }
-
-    fn clear(&mut self) {
-        for x in &mut self.0 {
-            *x = 0;
-        }
-    }
+const MAX_REPLAY_DEPTH: u8 = 32;
+
+/// A visual selection remembered for `gv`.
+#[derive(Clone, Copy)]
+struct LastVisual {
+    kind: VisualKind,
+    anchor: (u8, u8),
+    cursor: (u8, u8),
+    selected: Selection,
 }
 
 pub struct Sudoku {
     board: SudokuBoard,
     only_solution: Option<SudokuBoard>,
     settings: Rc<RefCell<Settings>>,
+    registers: Rc<RefCell<Registers>>,
     mode: Mode,
+    pending: Option<Pending>,
+    operator: Option<(Operator, Option<u8>)>,
 
-    highlight: u8,
+    register: Option<char>,
+    recording: Option<(char, Vec<Key>)>,
+    replay_depth: u8,
+    /// Whether keys were fed since the last keybind ran, leaving their own
+    /// keybind state that must not be flushed.
+    fed_keys: bool,
 
-    cmd: String,
+    last_change: Option<(String, Option<u8>)>,
+    /// The target of the `change` whose insert mode we're in, so the number
+    /// typed there is repeated together with it.
+    change_target: Option<String>,
+
+    highlight: u8,
+    search: Option<Query>,
+
+    cmd: CmdLine,
+    cmd_history: Rc<RefCell<CmdHistory>>,
+    messages: Rc<RefCell<Messages>>,
+    /// Lines shown above the command line until the next key, e.g. by
+    /// `:messages`.
+    shown: Vec<String>,
+    frame_cmds: Vec<String>,
+    /// Set when a config file was written, so the frame doesn't reload it.
+    wrote_config: bool,
 
     history: History,
 
+    /// Lost with the buffer, there's no saving them with the puzzle yet.
+    marks: HashMap<char, (u8, u8)>,
+    jumps: JumpList,
+
     selected: Selection,
+    visual_anchor: (u8, u8),
+    visual_prev: (u8, u8),
+    last_visual: Option<LastVisual>,
+    saved_selections: HashMap<char, Selection>,
 
     wrong: HashSet<(u8, u8)>,
 
     curr_keybind: String,
+    /// When the last key of `curr_keybind` was typed.
+    keybind_time: f64,
+    pub local_opts: LocalOpts,
     repeat: u8,
 
     col: u8,
     row: u8,
+    /// Cursors besides the main one, moved along with it.
+    cursors: Vec<(u8, u8)>,
 }
 
 impl Sudoku {
-    pub fn new(settings: Rc<RefCell<Settings>>) -> Self {
+    pub fn new(
+        settings: Rc<RefCell<Settings>>,
+        registers: Rc<RefCell<Registers>>,
+        cmd_history: Rc<RefCell<CmdHistory>>,
+        messages: Rc<RefCell<Messages>>,
+    ) -> Self {
         Self {
             wrong: HashSet::new(),
             highlight: 0,
+            search: None,
             history: History::default(),
+            marks: HashMap::new(),
+            jumps: JumpList::default(),
             only_solution: None,
             board: SudokuBoard(
                 [[if settings.borrow().opts.auto_fill_candidates {
@@ -80,21 +140,47 @@ impl Sudoku {
                 }; 9]; 9],
             ),
             settings: Rc::clone(&settings),
+            registers,
             mode: Mode::Normal,
+            pending: None,
+            operator: None,
 
-            selected: Selection::default(),
+            register: None,
+            recording: None,
+            replay_depth: 0,
+            fed_keys: false,
 
-            cmd: String::default(),
+            last_change: None,
+            change_target: None,
+
+            selected: Selection::default(),
+            saved_selections: HashMap::new(),
+            visual_anchor: (4, 4),
+            visual_prev: (4, 4),
+            last_visual: None,
+
+            cmd: CmdLine::default(),
+            cmd_history,
+            messages,
+            shown: vec![],
+            frame_cmds: vec![],
+            wrote_config: false,
 
             curr_keybind: String::default(),
+            keybind_time: 0.,
+            local_opts: LocalOpts::default(),
             repeat: 0,
 
             col: 4,
             row: 4,
+            cursors: vec![],
         }
     }
 
-    pub fn draw(&self, mut dimensions: Rect) {
+    /// Draws the buffer with a window's local options on top of its own.
+    pub fn draw(&self, mut dimensions: Rect, window_opts: &LocalOpts) {
+        let mut opts = self.opts();
+        window_opts.apply(&mut opts);
         let min_len = f32::min(dimensions.w, dimensions.h);
         let (side, box_size) = self.settings.borrow().get_lengths(min_len);
 
@@ -115,12 +201,13 @@ impl Sudoku {
         draw_box_lines(&self.settings.borrow(), &dimensions, side, box_size);
         draw_outlines(&self.settings.borrow(), &dimensions, side);
 
-        self.draw_grid(&dimensions, box_size);
+        self.draw_grid(&dimensions, box_size, &opts);
     }
 
-    fn draw_grid(&self, dimensions: &Rect, square_size: f32) {
+    fn draw_grid(&self, dimensions: &Rect, square_size: f32, opts: &Opts) {
+        let settings = self.settings.borrow();
         let text_params = TextParams {
-            font: Some(&self.settings.borrow().font),
+            font: Some(settings.font()),
             font_size: self.settings.borrow().get_num_font_size(square_size),
             font_scale: FONT_SCALE,
             color: self.settings.borrow().colors.normal_font,
@@ -134,7 +221,7 @@ impl Sudoku {
         let x_num_offset = self.settings.borrow().get_x_num_offset(square_size);
         let y_num_offset = self.settings.borrow().get_y_num_offset(square_size);
 
-        let highlight_size = self.settings.borrow().get_highlight_size(square_size);
+        let highlight_size = opts.get_highlight_size(square_size);
 
         let mut y = self.settings.borrow().lines.outer_width + dimensions.y;
         let mut x = self.settings.borrow().lines.outer_width + dimensions.x;
@@ -161,105 +248,43 @@ impl Sudoku {
                         }
                     }
 
-                    let mut check_corners = [true; 4];
-
-                    // SIDES
-                    if !neighbors[1] {
-                        draw_rectangle(
-                            x,
-                            y,
-                            square_size,
-                            highlight_size,
-                            self.settings.borrow().colors.visual_highlight_color,
-                        );
-                        check_corners[0] = false;
-                        check_corners[1] = false;
-                    }
-                    if !neighbors[3] {
-                        draw_rectangle(
-                            x,
-                            y,
-                            highlight_size,
-                            square_size,
-                            self.settings.borrow().colors.visual_highlight_color,
-                        );
-                        check_corners[0] = false;
-                        check_corners[3] = false;
-                    }
-                    if !neighbors[4] {
-                        draw_rectangle(
-                            x + square_size - highlight_size,
-                            y,
-                            highlight_size,
-                            square_size,
-                            self.settings.borrow().colors.visual_highlight_color,
-                        );
-                        check_corners[1] = false;
-                        check_corners[2] = false;
-                    }
-                    if !neighbors[6] {
-                        draw_rectangle(
-                            x,
-                            y + square_size - highlight_size,
-                            square_size,
-                            highlight_size,
-                            self.settings.borrow().colors.visual_highlight_color,
-                        );
-                        check_corners[2] = false;
-                        check_corners[3] = false;
-                    }
-
-                    if check_corners[0] {
-                        if !neighbors[0] {
-                            draw_rectangle(
-                                x,
-                                y,
-                                highlight_size,
-                                highlight_size,
-                                self.settings.borrow().colors.visual_highlight_color,
-                            );
-                        }
-                    }
-                    if check_corners[1] {
-                        if !neighbors[2] {
-                            draw_rectangle(
-                                x + square_size - highlight_size,
-                                y,
-                                highlight_size,
-                                highlight_size,
-                                self.settings.borrow().colors.visual_highlight_color,
-                            );
-                        }
-                    }
-                    if check_corners[2] {
-                        if !neighbors[7] {
-                            draw_rectangle(
-                                x + square_size - highlight_size,
-                                y + square_size - highlight_size,
-                                highlight_size,
-                                highlight_size,
-                                self.settings.borrow().colors.visual_highlight_color,
-                            );
-                        }
-                    }
-                    if check_corners[3] {
-                        if !neighbors[5] {
-                            draw_rectangle(
-                                x,
-                                y + square_size - highlight_size,
-                                highlight_size,
-                                highlight_size,
-                                self.settings.borrow().colors.visual_highlight_color,
-                            );
-                        }
-                    }
+                    draw_cell_outline(
+                        x,
+                        y,
+                        square_size,
+                        highlight_size,
+                        neighbors,
+                        self.settings.borrow().colors.visual_highlight_color,
+                    );
+                }
+                // draw the other cursors
+                if self.cursors.contains(&(i as u8, j as u8)) {
+                    draw_cell_outline(
+                        x,
+                        y,
+                        square_size,
+                        highlight_size,
+                        [false; 8],
+                        self.settings.borrow().colors.cursor_color,
+                    );
+                }
+                if let Some(query) = &self.search
+                    && query.matches(*n)
+                {
+                    draw_rectangle(
+                        x,
+                        y,
+                        square_size,
+                        square_size,
+                        self.settings.borrow().colors.search_color,
+                    );
                 }
                 // draw num
                 if *n != 0 {
                     if is_note(*n) {
                         // draw_highlights
                         if self.highlight != 0 && *n & (1 << (self.highlight - 1)) > 0 {
-                            if self.settings.borrow().opts.highlight_square_instead_of_note {
+                            if opts.highlight_square_instead_of_note {
                                 draw_rectangle(
                                     x,
                                     y,
@@ -284,11 +309,12 @@ impl Sudoku {
                         let x = x + x_note_offset;
                         draw_notes(
                             &self.settings.borrow(),
+                            opts.get_note_font_size(square_size),
                             square_size,
                             x,
                             note_y,
                             *n,
-                            &self.settings.borrow().font,
+                            self.settings.borrow().font(),
                         );
                     } else {
                         if self.highlight as u16 == *n {
@@ -300,18 +326,13 @@ impl Sudoku {
                                 self.settings.borrow().colors.highlight_color,
                             );
                         }
-                        if let Some(..) = self.wrong.get(&(i as u8, j as u8)) {
+                        if self.wrong.contains(&(i as u8, j as u8)) {
                             draw_rectangle(
                                 x,
                                 y,
                                 square_size,
                                 square_size,
-                                Color {
-                                    r: 1.0,
-                                    g: 0.0,
-                                    b: 0.0,
-                                    a: 0.8,
-                                },
+                                self.settings.borrow().colors.invalid_color,
                             );
                         }
                         let x = x + x_num_offset;
@@ -345,32 +366,35 @@ impl Sudoku {
             dimensions.h,
             self.settings.borrow().colors.status_bg,
         );
+        let settings = self.settings.borrow();
         let text_params = TextParams {
-            font: Some(&self.settings.borrow().font),
+            font: Some(settings.font()),
             font_size: self.settings.borrow().opts.command_font_size,
             font_scale: FONT_SCALE,
             color: self.settings.borrow().colors.status_font,
             ..Default::default()
         };
-        let text = format!("-- {} --", self.mode.to_string().to_uppercase());
+        let mut text = format!("-- {} --", self.mode.to_string().to_uppercase());
+        if let Some((name, _)) = &self.recording {
+            text += &format!(" recording @{name}");
+        }
         let centered = center_text(
             &text,
-            &self.settings.borrow().font,
+            self.settings.borrow().font(),
             self.settings.borrow().opts.command_font_size,
             *dimensions,
         );
 
         draw_text_ex(&text, dimensions.x, centered.y, text_params.clone());
 
-        let text;
-        if self.repeat > 0 {
-            text = format!("{}{}", self.repeat, self.curr_keybind)
+        let text = if self.repeat > 0 {
+            format!("{}{}", self.repeat, notation::pretty(&self.curr_keybind))
         } else {
-            text = self.curr_keybind.clone();
-        }
+            notation::pretty(&self.curr_keybind)
+        };
         let width = measure_text(
             &text,
-            Some(&self.settings.borrow().font),
+            Some(self.settings.borrow().font()),
             self.settings.borrow().opts.command_font_size,
             FONT_SCALE,
         )
@@ -378,6 +402,52 @@ impl Sudoku {
 
         let x = dimensions.x + dimensions.w - width;
         draw_text_ex(&text, x, centered.y, text_params);
+
+        self.draw_which_key(dimensions);
+    }
+
+    /// Lists the binds the pending keys could still become, once they've been
+    /// pending for `which_key_delay`.
+    fn draw_which_key(&self, dimensions: &Rect) {
+        let settings = self.settings.borrow();
+        if !settings.opts.which_key
+            || self.curr_keybind.is_empty()
+            || get_time() - self.keybind_time < settings.opts.which_key_delay as f64 / 1000.
+        {
+            return;
+        }
+        let modes = self.keymap_modes();
+        let mut binds: Vec<_> = settings
+            .keymaps
+            .iter()
+            .filter(|((m, keys), _)| {
+                modes.contains(m)
+                    && keys.len() > self.curr_keybind.len()
+                    && keys.starts_with(&self.curr_keybind)
+            })
+            .map(|((_, keys), action)| (notation::pretty(&keys[self.curr_keybind.len()..]), action))
+            .collect();
+        if binds.is_empty() {
+            return;
+        }
+        binds.sort();
+        let width = binds.iter().map(|x| x.0.chars().count()).max().unwrap_or(0);
+        let mut lines: Vec<_> = binds
+            .iter()
+            .map(|(keys, action)| format!("{keys:width$}  {action}"))
+            .collect();
+        if lines.len() > frame::POPUP_LINES {
+            let more = lines.len() - (frame::POPUP_LINES - 1);
+            lines.truncate(frame::POPUP_LINES - 1);
+            lines.push(format!("+{more} more"));
+        }
+        draw_popup(
+            &settings,
+            &lines,
+            None,
+            vec2(dimensions.x, dimensions.y),
+            (settings.colors.status_bg, settings.colors.status_font),
+        );
     }
 
     pub fn draw_cmd_line(&self, dimensions: &Rect) {
@@ -388,8 +458,9 @@ impl Sudoku {
             dimensions.h,
             self.settings.borrow().colors.cmd_bg,
         );
+        let settings = self.settings.borrow();
         let text_params = TextParams {
-            font: Some(&self.settings.borrow().font),
+            font: Some(settings.font()),
             font_size: self.settings.borrow().opts.command_font_size,
             font_scale: FONT_SCALE,
             color: self.settings.borrow().colors.cmd_font,
@@ -397,29 +468,73 @@ impl Sudoku {
         };
         let centered = center_text(
             "BIG BANANA PENCIL",
-            &self.settings.borrow().font,
+            self.settings.borrow().font(),
             self.settings.borrow().opts.command_font_size,
             *dimensions,
         );
-        draw_text_ex(&self.cmd, dimensions.x + 2.0, centered.y, text_params);
+        draw_text_ex(&self.cmd.text, dimensions.x + 2.0, centered.y, text_params);
+
+        if !self.shown.is_empty() {
+            let settings = self.settings.borrow();
+            // the latest lines, if they don't all fit
+            let first = self.shown.len().saturating_sub(frame::POPUP_LINES);
+            draw_popup(
+                &settings,
+                &self.shown[first..],
+                None,
+                vec2(dimensions.x, dimensions.y),
+                (settings.colors.cmd_bg, settings.colors.cmd_font),
+            );
+        }
+        if self.mode != Mode::Command {
+            return;
+        }
+        let settings = self.settings.borrow();
+        let offset = measure_text(
+            &self.cmd.text[..self.cmd.cursor],
+            Some(settings.font()),
+            settings.opts.command_font_size,
+            FONT_SCALE,
+        )
+        .width;
+        draw_rectangle(
+            dimensions.x + 2.0 + offset,
+            dimensions.y + 2.0,
+            1.0,
+            dimensions.h - 4.0,
+            settings.colors.cmd_font,
+        );
+        if let Some((candidates, idx)) = self.cmd.completions() {
+            draw_popup(
+                &settings,
+                candidates,
+                Some(idx),
+                vec2(dimensions.x + 2.0 + offset, dimensions.y),
+                (settings.colors.cmd_bg, settings.colors.cmd_font),
+            );
+        }
     }
 
-    pub fn update(&mut self) {
-        self.handle_input();
+    /// The modes whose keymaps apply, earlier ones first: `search` binds go
+    /// over normal ones while a search is shown.
+    fn keymap_modes(&self) -> Vec<String> {
+        let mode = self.mode.to_string();
+        if self.mode == Mode::Normal && self.search.is_some() {
+            vec!["search".to_string(), mode]
+        } else {
+            vec![mode]
+        }
     }
 
     pub fn try_keybind(&mut self) -> bool {
-        let mode = self.mode.to_string();
-        let action = if let Some(action) = self
-            .settings
-            .borrow()
-            .keymaps
-            .get(&(mode, self.curr_keybind.clone()))
-        {
-            action.clone()
-        } else {
+        let action = self.keymap_modes().into_iter().find_map(|mode| {
+            let keymaps = &self.settings.borrow().keymaps;
+            keymaps.get(&(mode, self.curr_keybind.clone())).cloned()
+        });
+        let Some(action) = action else {
             return false;
         };
+        self.fed_keys = false;
         if action.find(";").is_some() {
             let commands = action.split(";").collect::<Vec<_>>();
             if self.repeat > 0 {
@@ -433,151 +548,232 @@ impl Sudoku {
             let cmd = format!("{}{action}", self.repeat);
             self.process_cmd(&cmd);
         }
-        self.flush();
+        if !std::mem::take(&mut self.fed_keys) {
+            self.flush();
+        }
         true
     }
 
-    fn handle_input(&mut self) {
+    /// Runs a `;` separated list of commands, e.g. a frame keymap's action.
+    pub fn run(&mut self, action: &str) {
+        self.fed_keys = false;
+        for cmd in action.split(';') {
+            self.process_cmd(cmd);
+        }
+        if !std::mem::take(&mut self.fed_keys) {
+            self.flush();
+        }
+    }
+
+    /// Handles a key typed by the user, recording it if a macro is being
+    /// recorded.
+    pub fn press(&mut self, key: Key) {
+        self.shown.clear();
+        if let Some((_, keys)) = &mut self.recording {
+            keys.push(key);
+        }
+        self.feed(key);
+    }
+
+    fn feed(&mut self, key: Key) {
+        self.handle_key(key);
+        self.update_visual();
+    }
+
+    fn handle_key(&mut self, key: Key) {
         // global base case
-        if is_key_down(KeyCode::Escape) {
+        if key == Key::Code(KeyCode::Escape) {
+            if self.mode == Mode::Normal {
+                self.cursors.clear();
+                self.search = None;
+            }
+            self.save_visual();
             self.mode = Mode::Normal;
+            self.pending = None;
+            self.operator = None;
+            self.change_target = None;
             self.selected.clear();
             self.flush();
             return;
         }
 
+        if let Some(pending) = self.pending.take() {
+            if let Key::Char(c) = key {
+                self.resolve_pending(pending, c);
+            }
+            return;
+        }
+
+        if self.mode == Mode::Command {
+            self.handle_cmd_line_key(key);
+            return;
+        }
+
+        // special keys reach the keymaps encoded, see `Key::encode`
+        let c = key.encode();
         match &mut self.mode {
-            Mode::Normal => {
-                if let Some(c) = get_char_pressed() {
-                    match c {
-                        ':' => {
-                            self.mode = Mode::Command;
-                            self.cmd.clear();
-                            self.flush();
-                        }
-                        '0'..='9' => {
-                            self.repeat = self
-                                .repeat
-                                .saturating_mul(10)
-                                .saturating_add(c as u8 - b'0')
-                        }
-                        _ => self.update_keybind(c),
-                    }
+            Mode::Normal => match c {
+                ':' => {
+                    self.mode = Mode::Command;
+                    self.cmd.set(String::new());
+                    self.flush();
                 }
-            }
-            Mode::Command => {
-                if let Some(keycode) = get_last_key_pressed() {
-                    match keycode {
-                        KeyCode::Backspace => {
-                            if is_key_down(KeyCode::LeftControl) {
-                                while let Some(val) = self.cmd.pop() {
-                                    if let ' ' | '_' | '\'' = val {
-                                        break;
-                                    }
-                                }
-                            } else {
-                                self.cmd.pop();
-                            }
-                        }
-                        KeyCode::Enter => {
-                            self.process_cmd(&self.cmd.clone());
-                        }
-                        _ => {
-                            if let Some(ch) = get_char_pressed() {
-                                if ch.is_ascii() {
-                                    self.cmd.push(ch);
-                                }
-                            }
-                        }
-                    }
+                '0' if self.repeat == 0 => self.update_keybind(c),
+                '0'..='9' => {
+                    self.repeat = self
+                        .repeat
+                        .saturating_mul(10)
+                        .saturating_add(c as u8 - b'0')
                 }
-            }
-            Mode::Note => {
-                if let Some(c) = get_char_pressed() {
-                    match c {
-                        '0'..='9' => {
-                            self.process_cmd(&format!("{c}note"));
-                        }
-                        _ => self.update_keybind(c),
-                    }
+                _ => self.update_keybind(c),
+            },
+            Mode::Command => unreachable!(),
+            Mode::Note => match c {
+                '0'..='9' => {
+                    self.process_cmd(&format!("{c}note"));
                 }
-            }
-            Mode::Insert => {
-                if let Some(c) = get_char_pressed() {
-                    match c {
-                        '0'..='9' => {
-                            self.process_cmd(&format!("{c}insert"));
-                        }
-                        _ => self.update_keybind(c),
-                    }
+                _ => self.update_keybind(c),
+            },
+            Mode::Insert => match c {
+                '0'..='9' => {
+                    self.process_cmd(&format!("{c}insert"));
                 }
-            }
-            Mode::Go(row) => {
-                if let Some(c) = get_char_pressed() {
-                    match c {
-                        '1'..='9' => {
-                            if *row == 0 {
-                                *row = c as u8 - b'0';
-                            } else {
-                                self.process_cmd(&format!("{}{c}go", self.row.clone()));
-                            }
-                        }
-                        '0' => (),
-                        _ => self.update_keybind(c),
+                _ => self.update_keybind(c),
+            },
+            Mode::Go(row) => match c {
+                '1'..='9' => {
+                    if *row == 0 {
+                        *row = c as u8 - b'0';
+                    } else {
+                        let row = std::mem::take(row);
+                        self.process_cmd(&format!("{row}{c}go"));
                     }
                 }
-            }
-            Mode::Custom(..) => {
-                if let Some(c) = get_char_pressed() {
-                    match c {
-                        '0'..='9' => {
-                            self.repeat = self
-                                .repeat
-                                .saturating_mul(10)
-                                .saturating_add(c as u8 - b'0');
-                        }
-                        _ => self.update_keybind(c),
-                    }
+                '0' => (),
+                _ => self.update_keybind(c),
+            },
+            Mode::Custom(..) | Mode::Visual(..) => match c {
+                '0' if self.repeat == 0 => self.update_keybind(c),
+                '0'..='9' => {
+                    self.repeat = self
+                        .repeat
+                        .saturating_mul(10)
+                        .saturating_add(c as u8 - b'0');
                 }
-            }
-            Mode::Highlight => {
-                if let Some(c) = get_char_pressed() {
-                    match c {
-                        '0'..='9' => {
-                            self.highlight = c as u8 - b'0';
-                            self.mode = Mode::Normal;
-                        }
-                        _ => self.update_keybind(c),
-                    }
+                _ => self.update_keybind(c),
+            },
+            Mode::Highlight => match c {
+                '0'..='9' => {
+                    self.highlight = c as u8 - b'0';
+                    self.mode = Mode::Normal;
                 }
+                _ => self.update_keybind(c),
+            },
+        }
+    }
+
+    fn handle_cmd_line_key(&mut self, key: Key) {
+        let tab = Key::Code(KeyCode::Tab);
+        let shift_tab = Key::code(KeyCode::Tab, SHIFT);
+        if key != tab && key != shift_tab {
+            self.cmd.end_completion();
+        }
+        match key {
+            Key::Code(KeyCode::Backspace) => self.cmd.backspace(),
+            _ if key == Key::code(KeyCode::Backspace, CTRL) => self.cmd.delete_word(),
+            Key::Code(KeyCode::Enter) => {
+                let line = self.cmd.text.clone();
+                self.cmd_history.borrow_mut().push(&line);
+                self.process_cmd(&line);
             }
+            Key::Code(KeyCode::Tab) => self.cmd.complete(completions, false),
+            _ if key == shift_tab => self.cmd.complete(completions, true),
+            Key::Code(KeyCode::Left) => self.cmd.left(),
+            Key::Code(KeyCode::Right) => self.cmd.right(),
+            Key::Code(KeyCode::Home) => self.cmd.home(),
+            Key::Code(KeyCode::End) => self.cmd.end(),
+            Key::Code(KeyCode::Delete) => self.cmd.delete(),
+            Key::Code(KeyCode::Up) => self.cmd.history_prev(self.cmd_history.borrow().entries()),
+            Key::Code(KeyCode::Down) => self.cmd.history_next(self.cmd_history.borrow().entries()),
+            Key::Char(ch) if ch == ctrl('r') => self.pending = Some(Pending::CmdRegister),
+            Key::Char(ch) if ch.is_ascii() && !ch.is_control() => {
+                self.cmd.insert(&ch.to_string());
+            }
+            _ => (),
+        }
+    }
+
+    /// Inserts a register's keys into the command line.
+    fn insert_register(&mut self, name: char) {
+        let register = self.registers.borrow().get(name).cloned();
+        if let Some(Register::Keys(keys)) = register {
+            let text: String = keys
+                .iter()
+                .filter_map(|key| match key {
+                    Key::Char(c) => Some(*c),
+                    Key::Code(_) => None,
+                })
+                .collect();
+            self.cmd.insert(&text);
+        }
+    }
+
+    fn resolve_pending(&mut self, pending: Pending, name: char) {
+        match pending {
+            Pending::Record => self.start_recording(name),
+            Pending::Play(count) => self.play_register(name, count),
+            Pending::Register => self.select_register(name),
+            Pending::Bookmark => self.set_bookmark(name),
+            Pending::Jump => self.jump_to_bookmark(name),
+            Pending::CmdRegister => self.insert_register(name),
         }
     }
 
     fn update_keybind(&mut self, c: char) {
         self.curr_keybind += &c.to_string();
+        self.keybind_time = get_time();
+        // with a timeout, a bind that starts a longer one waits for `tick`
+        if self.settings.borrow().opts.timeoutlen > 0 && self.longer_keymap_exists() {
+            return;
+        }
+        if !self.try_keybind() && !self.longer_keymap_exists() {
+            self.cancel_keybind();
+        }
+    }
+
+    /// Resolves the pending keys once `timeoutlen` has passed without more.
+    pub fn tick(&mut self) {
+        let timeoutlen = self.settings.borrow().opts.timeoutlen;
+        if timeoutlen == 0
+            || self.curr_keybind.is_empty()
+            || get_time() - self.keybind_time < timeoutlen as f64 / 1000.
+        {
+            return;
+        }
         if !self.try_keybind() {
-            if !self.matching_keymap_exists() {
-                self.flush();
-            }
+            self.cancel_keybind();
         }
     }
 
-    fn matching_keymap_exists(&self) -> bool {
-        let idx = self.curr_keybind.len();
-        let curr_mode = self.mode.to_string();
-        for ((mode, keybind), _) in &self.settings.borrow().keymaps {
-            if *mode != curr_mode {
-                continue;
-            }
-            if keybind.len() < idx {
-                continue;
-            }
-            if keybind[..idx] == self.curr_keybind {
-                return true;
-            }
+    fn cancel_keybind(&mut self) {
+        self.flush();
+        // an unknown motion cancels a pending operator
+        if self.operator.take().is_some() {
+            self.mode = Mode::Normal;
         }
-        false
+    }
+
+    fn longer_keymap_exists(&self) -> bool {
+        let modes = self.keymap_modes();
+        self.settings
+            .borrow()
+            .keymaps
+            .keys()
+            .any(|(mode, keybind)| {
+                modes.contains(mode)
+                    && keybind.len() > self.curr_keybind.len()
+                    && keybind.starts_with(&self.curr_keybind)
+            })
     }
 
     fn flush(&mut self) {
@@ -587,6 +783,7 @@ impl Sudoku {
 
     #[rustfmt::skip]
     fn process_cmd(&mut self, cmd: &str) {
+        let from_cmd_line = self.mode == Mode::Command;
         let mut trim = cmd.trim();
 
         let mut repeat: u8 = 0;
@@ -596,200 +793,319 @@ impl Sudoku {
                 repeat_end = i;
                 break;
             }
-            if let Some(first) = repeat.checked_mul(10) {
-                if let Some(val) = first.checked_add(c as u8 - b'0') {
+            if let Some(first) = repeat.checked_mul(10)
+                && let Some(val) = first.checked_add(c as u8 - b'0') {
                     repeat = val;
                 }
-            }
         }
         trim = &trim[repeat_end..];
 
         let mut args = &trim[0..0];
         let str = if let Some(space) = trim.find(' ') {
-            args = &trim[space..].trim();
+            args = trim[space..].trim();
             &trim[..space]
         } else {
             trim
         };
         let repeat = if repeat == 0 {None} else {Some(repeat)};
+        // whether a change went through, making it repeatable with `.`
+        let mut changed = false;
         match str {
-            "insert" | "i"   => self.insert(repeat),
-            "note"   | "n"   => self.note(repeat),
+            "insert" | "i"   => changed = self.insert(repeat),
+            "note"   | "n"   => changed = self.note(repeat, args),
             "go"     | "g"   => self.go(repeat),
 
             "move"   | "mov" => self.mov(args, repeat),
+            "extend"         => self.extend(args, repeat),
+            "search"         => self.search(args, repeat),
+            "nohlsearch" | "noh" => self.search = None,
+            "visual"         => self.visual(args),
+            "select"         => self.select(args),
+            "cursor"         => self.cursor(args, repeat),
+            "bookmark"       => self.bookmark(args),
+            "jump"           => self.jump(args),
             "mode"           => self.mode(args),
             "mark"           => self.mark(),
             "fill"           => { 
                 let mut changes = Vec::with_capacity(81);
                 self.board.fill_cell_candidates(&mut changes);
                 self.history.record_change(changes);
+                changed = true;
             },
             "import"         => self.import_clipboard(),
             "highlight"      => self.highlight(repeat),
             "set" | "se"     => self.set(args),
+            "colorscheme" | "colo" => self.colorscheme(args),
+            "map"            => self.map(args, true),
+            "noremap" | "no" => self.map(args, false),
+            "unmap"          => self.unmap(args),
+            "maps"           => self.list_maps(args),
+            "feed"           => self.feed_keys(args, repeat),
+            "checkmaps"      => self.check_maps(),
+            "messages" | "mes" => self.messages(args),
+            "mkconfig" | "mkconfig!" => self.mkconfig(args, str.ends_with('!')),
             "undo"           => self.undo(),
             "redo"           => self.redo(),
-            _ => {
-                self.cmd_log(format!("Invalid command: {str}"));
-            }
+            "record"         => self.record(args),
+            "play"           => self.play(args, repeat),
+            "clear"          => changed = self.clear(args),
+            "change"         => changed = self.change(args, repeat),
+            "operator"       => self.operator(args, repeat),
+            "object"         => self.object(args, repeat),
+            "register"       => self.register(args),
+            "yank"           => self.yank(args),
+            "put"            => changed = self.put(args),
+            "repeat"         => self.repeat_change(repeat),
+            // not ours, maybe the frame knows it
+            _ => self.frame_cmds.push(cmd.trim().to_string()),
         }
-        if self.mode == Mode::Command {
+        if changed {
+            let cmd = match &self.change_target {
+                Some(target) if matches!(str, "insert" | "i") && self.mode == Mode::Insert => {
+                    format!("change {target}")
+                }
+                _ => format!("{str} {args}"),
+            };
+            self.last_change = Some((cmd, repeat));
+        }
+        if from_cmd_line && self.mode == Mode::Command {
             self.mode = Mode::Normal;
         }
     }
 
-    fn cmd_log(&mut self, err_msg: String) {
-        self.cmd = err_msg;
+    /// Runs a single command line, like typing it after `:`.
+    pub fn execute(&mut self, line: &str) {
+        self.process_cmd(line);
+        self.flush();
     }
 
-    // COMMANDS
-    fn mov(&mut self, args: &str, repeat: Option<u8>) {
-        if let Some(_) = args.find(' ') {
-            self.cmd_log("Invalid usage: mov u/d/l/r".to_string())
+    /// Commands left for the frame to run.
+    pub fn take_frame_cmds(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.frame_cmds)
+    }
+
+    /// Whether a config file was written since the last call.
+    pub fn take_wrote_config(&mut self) -> bool {
+        std::mem::take(&mut self.wrote_config)
+    }
+
+    /// The options as this buffer sees them, `:setlocal` ones included.
+    pub fn opts(&self) -> Opts {
+        let mut opts = self.settings.borrow().opts.clone();
+        self.local_opts.apply(&mut opts);
+        opts
+    }
+
+    pub fn cmd_log(&mut self, err_msg: String) {
+        self.messages.borrow_mut().push(err_msg.clone());
+        self.cmd.set(err_msg);
+    }
+
+    /// Logs a single line, or shows several one per line like `:messages`.
+    pub fn show(&mut self, mut lines: Vec<String>) {
+        if lines.len() == 1 {
+            self.cmd_log(lines.remove(0));
+        } else {
+            self.shown = lines;
         }
+    }
 
-        let repeat = repeat.unwrap_or(1);
+    /// `messages` shows the latest messages, `messages clear` forgets them.
+    fn messages(&mut self, args: &str) {
         match args {
-            "u" | "up" => {
-                for _ in 0..repeat {
-                    if self.row > 0 {
-                        self.row -= 1;
-                    } else {
-                        break;
-                    }
+            "" => {
+                self.shown = self.messages.borrow().lines().cloned().collect();
+                if self.shown.is_empty() {
+                    self.cmd.set("No messages".to_string());
                 }
             }
-            "d" | "down" => {
-                for _ in 0..repeat {
-                    if self.row < 8 {
-                        self.row += 1;
-                    } else {
-                        break;
-                    }
-                }
+            "clear" => self.messages.borrow_mut().clear(),
+            _ => self.cmd_log("Invalid usage: messages [clear]".to_string()),
+        }
+    }
+
+    // COMMANDS
+    fn mov(&mut self, args: &str, repeat: Option<u8>) {
+        let motion = unwrap_or_else!(Motion::parse(args), {
+            self.cmd_log(format!("Invalid motion: {args}"));
+            return;
+        });
+        if let Some((op, count)) = self.operator.take() {
+            self.run_operator(op, count, repeat, args, true);
+            return;
+        }
+        let wrap = self.settings.borrow().opts.wrap_around;
+        if let Some(pos) = motion.target(&self.board, (self.row, self.col), repeat, wrap) {
+            if motion.is_jump() {
+                self.jump_to(pos);
+            } else {
+                (self.row, self.col) = pos;
             }
-            "l" | "left" => {
-                for _ in 0..repeat {
-                    if self.col > 0 {
-                        self.col -= 1;
-                    } else {
-                        break;
-                    }
-                }
+        }
+        for i in 0..self.cursors.len() {
+            if let Some(pos) = motion.target(&self.board, self.cursors[i], repeat, wrap) {
+                self.cursors[i] = pos;
             }
-            "r" | "right" => {
-                for _ in 0..repeat {
-                    if self.col < 8 {
-                        self.col += 1;
-                    } else {
-                        break;
-                    }
-                }
+        }
+        self.merge_cursors();
+    }
+
+    /// Moves like `mov`, marking every cell passed on the way.
+    fn extend(&mut self, args: &str, repeat: Option<u8>) {
+        let from = (self.row, self.col);
+        self.mov(args, repeat);
+        let to = (self.row, self.col);
+        if from == to {
+            return;
+        }
+        // like with `mark; move`, the cell under the cursor isn't marked yet
+        for (y, x) in path(from, to).into_iter().filter(|pos| *pos != to) {
+            self.selected.set(y, x);
+        }
+    }
+
+    fn search(&mut self, args: &str, repeat: Option<u8>) {
+        let forward = match args {
+            "" => {
+                self.mode = Mode::Command;
+                self.cmd.set("search ".to_string());
+                return;
+            }
+            "next" => true,
+            "prev" => false,
+            query => {
+                self.search = Some(unwrap_or_else!(Query::parse(query), {
+                    self.cmd_log(format!("Invalid search: {query}"));
+                    return;
+                }));
+                true
             }
-            _ => self.cmd_log("Invalid usage: mov u/d/l/r".to_string()),
+        };
+        let query = unwrap_or_else!(self.search, {
+            self.cmd_log("No previous search".to_string());
+            return;
+        });
+        let mut pos = (self.row, self.col);
+        for _ in 0..repeat.unwrap_or(1) {
+            pos = unwrap_or_else!(
+                scan(pos, forward, true, |p| query.matches(self.board[p])),
+                {
+                    self.cmd_log("Pattern not found".to_string());
+                    return;
+                }
+            );
         }
+        self.jump_to(pos);
     }
 
-    fn insert(&mut self, repeat: Option<u8>) {
+    /// Inserts `repeat` at the cursors, or enters insert mode without it.
+    /// Returns whether it inserted.
+    fn insert(&mut self, repeat: Option<u8>) -> bool {
         if let Some(num) = repeat {
             if !(1..=9).contains(&num) {
-                self.cmd = "Invalid usage: <num>insert".to_string();
-                return;
+                self.cmd_log("Invalid usage: <num>insert".to_string());
+                return false;
             }
 
-            let before = self.board[(self.row, self.col)];
-            self.board[(self.row, self.col)] = num as u16;
-
-            if self.settings.borrow().opts.check_input {
-                if let Some(solution) = &self.only_solution {
-                    if solution[(self.row, self.col)] != num as u16 {
-                        self.board[(self.row, self.col)] = before;
-                        return;
-                    }
-                } else {
-                    let mut clone = self.board.clone();
-
-                    match clone.solve() {
-                        BacktrackResult::NoSolution => {
-                            if self.settings.borrow().opts.remove_invalid {
-                                self.board[(self.row, self.col)] = before;
-                                return;
-                            } else {
-                                self.wrong.insert((self.row, self.col));
-                            }
-                        }
-                        BacktrackResult::OneSolution(solution) => {
-                            self.only_solution = Some(solution);
-                        }
-                        BacktrackResult::MoreSolutions => (),
-                    }
-                }
+            let mut changes = vec![];
+            for pos in self.cursor_positions() {
+                self.insert_at(pos, num, &mut changes);
             }
-            let mut changes = vec![Change {
-                pos: (self.row, self.col),
-                before,
-                after: num as u16,
-            }];
-            if self.settings.borrow().opts.auto_candidate_elimination {
-                self.board
-                    .fix_notes_around(self.row, self.col, &mut changes);
+            if !changes.is_empty() {
+                self.history.record_change(changes);
             }
-            self.history.record_change(changes);
+            true
         } else {
             self.mode = Mode::Insert;
+            self.change_target = None;
+            false
         }
     }
 
-    fn note(&mut self, repeat: Option<u8>) {
-        if let Some(note) = repeat {
-            let mut changes = vec![];
-            if !(1..=9).contains(&note) {
-                self.cmd = "Invalid usage: <note>note".to_string();
-                return;
-            }
+    fn insert_at(&mut self, pos: (u8, u8), num: u8, changes: &mut Vec<Change>) {
+        let before = self.board[pos];
+        self.board[pos] = num as u16;
 
-            if !self.selected.get(self.row, self.col) {
-                let cell = &mut self.board[(self.row, self.col)];
-                if *cell == 0 || is_note(*cell) {
-                    let n = *cell;
-                    n_bit_on(cell, NOTE_FLAG);
-                    toggle_bit(cell, note - 1);
-                    changes.push(Change {
-                        pos: (self.row, self.col),
-                        before: n,
-                        after: *cell,
-                    });
-                } else {
-                    self.cmd_log("Err: Cell is already filled with a number".to_string());
-                }
-            }
-            for (i, row) in self.selected.0.iter().enumerate() {
-                if *row == 0 {
-                    continue;
+        if self.opts().check_input {
+            if let Some(solution) = &self.only_solution {
+                if solution[pos] != num as u16 {
+                    self.board[pos] = before;
+                    return;
                 }
-                for col in 0..9 {
-                    if *row & (1 << col) > 0 {
-                        let cell = &mut self.board[(i, col)];
-                        if *cell == 0 || is_note(*cell) {
-                            let n = *cell;
-                            n_bit_on(cell, NOTE_FLAG);
-                            toggle_bit(cell, note - 1);
-                            changes.push(Change {
-                                pos: (i as u8, col as u8),
-                                before: n,
-                                after: *cell,
-                            });
+            } else {
+                let mut clone = self.board;
+
+                match clone.solve() {
+                    BacktrackResult::NoSolution => {
+                        if self.opts().remove_invalid {
+                            self.board[pos] = before;
+                            return;
+                        } else {
+                            self.wrong.insert(pos);
                         }
                     }
+                    BacktrackResult::OneSolution(solution) => {
+                        self.only_solution = Some(solution);
+                    }
+                    BacktrackResult::MoreSolutions => (),
+                }
+            }
+        }
+        changes.push(Change {
+            pos,
+            before,
+            after: num as u16,
+        });
+        if self.opts().auto_candidate_elimination {
+            self.board.fix_notes_around(pos.0, pos.1, changes);
+        }
+    }
+
+    /// Toggles the note `repeat` in the target cells, or enters note mode
+    /// without it. Returns whether it toggled.
+    fn note(&mut self, repeat: Option<u8>, args: &str) -> bool {
+        if let Some(note) = repeat {
+            if !(1..=9).contains(&note) {
+                self.cmd_log("Invalid usage: <note>note [target]".to_string());
+                return false;
+            }
+            let cells = unwrap_or_else!(self.target_cells(args), {
+                return false;
+            });
+
+            let mut changes = vec![];
+            for pos in cells {
+                let mut notes = self.board[pos];
+                n_bit_on(&mut notes, NOTE_FLAG);
+                toggle_bit(&mut notes, note - 1);
+                if !self.note_at(pos, notes, &mut changes) && pos == (self.row, self.col) {
+                    self.cmd_log("Err: Cell is already filled with a number".to_string());
                 }
             }
             if !changes.is_empty() {
                 self.history.record_change(changes);
             }
+            true
         } else {
             self.mode = Mode::Note;
+            false
+        }
+    }
+
+    /// Sets the notes of a cell, unless it holds a number. Returns whether
+    /// they were set.
+    fn note_at(&mut self, pos: (u8, u8), notes: u16, changes: &mut Vec<Change>) -> bool {
+        let before = self.board[pos];
+        if before != 0 && !is_note(before) {
+            return false;
         }
+        self.board[pos] = notes;
+        changes.push(Change {
+            pos,
+            before,
+            after: notes,
+        });
+        true
     }
 
     fn go(&mut self, repeat: Option<u8>) {
@@ -797,18 +1113,79 @@ impl Sudoku {
             let y = goto / 10;
             let x = goto % 10;
 
-            if y <= 0 || y > 9 || x <= 0 {
+            if y == 0 || y > 9 || x == 0 {
                 self.cmd_log("Invalid usage: <y><x>go".to_string());
                 return;
             }
 
-            self.row = y - 1;
-            self.col = x - 1;
+            self.jump_to((y - 1, x - 1));
         } else {
             self.mode = Mode::Go(0);
         }
     }
 
+    /// Moves the cursor, remembering where it was in the jump list.
+    fn jump_to(&mut self, pos: (u8, u8)) {
+        let curr = (self.row, self.col);
+        if curr != pos {
+            self.jumps.push(curr);
+            (self.row, self.col) = pos;
+        }
+    }
+
+    fn bookmark(&mut self, args: &str) {
+        let mut chars = args.chars();
+        match (chars.next(), chars.next()) {
+            (None, _) => self.pending = Some(Pending::Bookmark),
+            (Some(name), None) => self.set_bookmark(name),
+            _ => self.cmd_log("Invalid usage: bookmark [a-z]".to_string()),
+        }
+    }
+
+    fn set_bookmark(&mut self, name: char) {
+        if name.is_ascii_lowercase() {
+            self.marks.insert(name, (self.row, self.col));
+        } else {
+            self.cmd_log(format!("Invalid mark: {name}"));
+        }
+    }
+
+    fn jump(&mut self, args: &str) {
+        let pos = match args {
+            "" => {
+                self.pending = Some(Pending::Jump);
+                return;
+            }
+            "older" => self.jumps.older((self.row, self.col)),
+            "newer" => self.jumps.newer(),
+            _ => {
+                let mut chars = args.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(name), None) => {
+                        self.jump_to_bookmark(name);
+                        return;
+                    }
+                    _ => {
+                        self.cmd_log("Invalid usage: jump [a-z]/older/newer".to_string());
+                        return;
+                    }
+                }
+            }
+        };
+        // moving through the jump list itself doesn't add to it
+        if let Some(pos) = pos {
+            (self.row, self.col) = pos;
+        }
+    }
+
+    fn jump_to_bookmark(&mut self, name: char) {
+        let pos = unwrap_or_else!(self.marks.get(&name).copied(), {
+            self.cmd_log(format!("Mark not set: {name}"));
+            return;
+        });
+        self.jump_to(pos);
+    }
+
     fn highlight(&mut self, repeat: Option<u8>) {
         if let Some(val) = repeat {
             self.highlight = val;
@@ -818,7 +1195,157 @@ impl Sudoku {
     }
 
     fn mode(&mut self, mode: &str) {
-        self.mode = Mode::Custom(mode.to_string());
+        self.change_target = None;
+        match Mode::from_name(mode) {
+            Mode::Visual(kind) => self.start_visual(kind),
+            mode => self.mode = mode,
+        }
+    }
+
+    fn visual(&mut self, args: &str) {
+        match args {
+            "swap" => {
+                let cursor = (self.row, self.col);
+                (self.row, self.col) = self.visual_anchor;
+                self.visual_anchor = cursor;
+                self.visual_prev = (self.row, self.col);
+            }
+            "reselect" => {
+                let last = unwrap_or_else!(self.last_visual, {
+                    self.cmd_log("No previous visual selection".to_string());
+                    return;
+                });
+                self.mode = Mode::Visual(last.kind);
+                self.visual_anchor = last.anchor;
+                self.visual_prev = last.cursor;
+                (self.row, self.col) = last.cursor;
+                self.selected = last.selected;
+            }
+            "exit" => {
+                self.save_visual();
+                self.selected.clear();
+                self.mode = Mode::Normal;
+            }
+            kind => match VisualKind::parse(kind) {
+                Some(kind) => self.start_visual(kind),
+                None => self.cmd_log(
+                    "Invalid usage: visual [path/block/row/col/box/swap/reselect/exit]".to_string(),
+                ),
+            },
+        }
+    }
+
+    fn start_visual(&mut self, kind: VisualKind) {
+        // switching between kinds keeps the anchor, like in vim
+        if !matches!(self.mode, Mode::Visual(..)) {
+            self.visual_anchor = (self.row, self.col);
+            self.selected.clear();
+        }
+        self.visual_prev = (self.row, self.col);
+        self.mode = Mode::Visual(kind);
+    }
+
+    fn save_visual(&mut self) {
+        if let Mode::Visual(kind) = self.mode {
+            self.last_visual = Some(LastVisual {
+                kind,
+                anchor: self.visual_anchor,
+                cursor: (self.row, self.col),
+                selected: self.selected,
+            });
+        }
+    }
+
+    /// Brings the selection up to date with the cursor after every key.
+    fn update_visual(&mut self) {
+        let Mode::Visual(kind) = self.mode else {
+            return;
+        };
+        let cursor = (self.row, self.col);
+        if kind == VisualKind::Path {
+            for (y, x) in path(self.visual_prev, cursor) {
+                self.selected.set(y, x);
+            }
+        } else {
+            self.selected.clear();
+            for (y, x) in kind.cells(self.visual_anchor, cursor) {
+                self.selected.set(y, x);
+            }
+        }
+        self.visual_prev = cursor;
+    }
+
+    fn select(&mut self, args: &str) {
+        let (op, terms) = args.split_once(' ').unwrap_or((args, ""));
+        let curr = self.selected;
+        let selection = |terms| {
+            Selection::matching(
+                terms,
+                &self.board,
+                (self.row, self.col),
+                &self.saved_selections,
+            )
+        };
+        let result = match op {
+            "none" => Ok(Selection::default()),
+            "save" => {
+                let mut chars = terms.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(name), None) => {
+                        self.saved_selections.insert(name, curr);
+                        return;
+                    }
+                    _ => Err("Invalid usage: select save <name>".to_string()),
+                }
+            }
+            "add" => selection(terms).map(|s| curr.union(&s)),
+            "sub" => selection(terms).map(|s| curr.difference(&s)),
+            "and" => selection(terms).map(|s| curr.intersection(&s)),
+            "" => Err("Invalid usage: select [add/sub/and] <predicate>".to_string()),
+            _ => selection(args),
+        };
+        match result {
+            Ok(selected) => {
+                self.selected = selected;
+                self.cmd_log(format!("{} cells selected", selected.len()));
+            }
+            Err(err) => self.cmd_log(err),
+        }
+    }
+
+    fn cursor(&mut self, args: &str, repeat: Option<u8>) {
+        let (action, motion) = args.split_once(' ').unwrap_or((args, ""));
+        match action {
+            "add" => {
+                let motion = unwrap_or_else!(Motion::parse(motion), {
+                    self.cmd_log(format!("Invalid motion: {motion}"));
+                    return;
+                });
+                let wrap = self.settings.borrow().opts.wrap_around;
+                let curr = (self.row, self.col);
+                if let Some(pos) = motion.target(&self.board, curr, repeat, wrap) {
+                    self.cursors.push(curr);
+                    (self.row, self.col) = pos;
+                }
+            }
+            "selection" => {
+                self.save_visual();
+                if matches!(self.mode, Mode::Visual(..)) {
+                    self.mode = Mode::Normal;
+                }
+                self.cursors.extend(self.selected.cells());
+                self.selected.clear();
+            }
+            "clear" => self.cursors.clear(),
+            _ => self.cmd_log("Invalid usage: cursor [add <motion>/selection/clear]".to_string()),
+        }
+        self.merge_cursors();
+    }
+
+    /// Drops cursors that ended up on the same cell as another one.
+    fn merge_cursors(&mut self) {
+        let mut seen = HashSet::from([(self.row, self.col)]);
+        self.cursors.retain(|pos| seen.insert(*pos));
     }
 
     fn mark(&mut self) {
@@ -834,15 +1361,15 @@ impl Sudoku {
         };
         match clipboard.get_text() {
             Ok(text) => {
-                let new = unwrap_or_else!(self.board.from_str(&text), {
+                let new = unwrap_or_else!(SudokuBoard::parse(&text), {
                     self.cmd_log("Invalid sudoku".to_string());
                     return;
                 });
-                if self.settings.borrow().opts.auto_fill_candidates {
+                if self.opts().auto_fill_candidates {
                     self.board.fill_cell_candidates(&mut vec![]);
                 }
 
-                let mut new_new = new.clone();
+                let mut new_new = new;
 
                 match new_new.solve() {
                     BacktrackResult::NoSolution => {
@@ -858,7 +1385,7 @@ impl Sudoku {
                 }
 
                 self.board = new;
-                if self.settings.borrow().opts.auto_fill_candidates {
+                if self.opts().auto_fill_candidates {
                     self.board.fill_cell_candidates(&mut vec![]);
                 }
             }
@@ -868,44 +1395,575 @@ impl Sudoku {
         }
     }
 
+    /// `set` lists the changed options, `set all` every option, and anything
+    /// else is applied argument by argument, see `options::set`.
     fn set(&mut self, args: &str) {
-        let idx = unwrap_or_else!(args.find("="), {
+        let mut settings = self.settings.borrow_mut();
+        let Settings {
+            lines,
+            colors,
+            opts,
+            ..
+        } = &mut *settings;
+        let msgs = if args.is_empty() || args == "all" {
+            options::list(lines, colors, opts, args == "all")
+        } else {
+            args.split_whitespace()
+                .filter_map(|arg| match options::set(arg, lines, colors, opts) {
+                    Ok(msg) => msg,
+                    Err(err_msg) => Some(err_msg),
+                })
+                .collect()
+        };
+        drop(settings);
+        if !msgs.is_empty() {
+            self.show(msgs);
+        }
+        // sizes might have changed
+        self.frame_cmds.push("redraw".to_string());
+    }
+
+    /// `map {modes} {keys} {keys}` binds to keys that are fed back through the
+    /// keymaps, `noremap {modes} {keys} {action}` binds to commands.
+    fn map(&mut self, args: &str, recursive: bool) {
+        let mut split = args.splitn(3, ' ');
+        let (Some(modes), Some(lhs), Some(rhs)) = (split.next(), split.next(), split.next()) else {
+            let name = if recursive { "map" } else { "noremap" };
+            self.cmd_log(format!(
+                "Invalid usage: {name} {{modes}} {{keys}} {{action}}"
+            ));
+            return;
+        };
+        if let Some(mode) = self.unknown_mode(modes) {
+            self.cmd_log(format!("Unknown mode: {mode}"));
+            return;
+        }
+        let leader = self.settings.borrow().opts.leader.clone();
+        let lhs = unwrap_or_else!(notation::parse(lhs, &leader).ok(), {
+            self.cmd_log(format!("Invalid keys: {lhs}"));
             return;
         });
-        let property = &args[0..idx];
-        match property.trim() {
-            "candidate-elimination" => match &args[(idx + 1)..] {
-                "true" => self.settings.borrow_mut().opts.auto_candidate_elimination = true,
-                "false" => self.settings.borrow_mut().opts.auto_candidate_elimination = false,
-                _ => (),
-            },
-            "check-input" => match &args[(idx + 1)..] {
-                "true" => self.settings.borrow_mut().opts.check_input = true,
-                "false" => self.settings.borrow_mut().opts.check_input = false,
-                _ => (),
-            },
-            "highlight-square" => match &args[(idx + 1)..] {
-                "true" => {
-                    self.settings
-                        .borrow_mut()
-                        .opts
-                        .highlight_square_instead_of_note = true
-                }
-                "false" => {
-                    self.settings
-                        .borrow_mut()
-                        .opts
-                        .highlight_square_instead_of_note = false
+        let action = if recursive {
+            if let Err(err_msg) = notation::parse(rhs, &leader) {
+                self.cmd_log(err_msg);
+                return;
+            }
+            format!("feed {}", rhs.trim())
+        } else {
+            rhs.trim().to_string()
+        };
+        let modes: Vec<_> = modes.split(',').collect();
+        for mode in &modes {
+            self.settings
+                .borrow_mut()
+                .keymaps
+                .insert((mode.to_string(), lhs.clone()), action.clone());
+        }
+        self.persist_keymap(&modes, &lhs, Some(&action));
+    }
+
+    fn unmap(&mut self, args: &str) {
+        let Some((modes, lhs)) = args.split_once(' ') else {
+            self.cmd_log("Invalid usage: unmap {modes} {keys}".to_string());
+            return;
+        };
+        if let Some(mode) = self.unknown_mode(modes) {
+            self.cmd_log(format!("Unknown mode: {mode}"));
+            return;
+        }
+        let leader = self.settings.borrow().opts.leader.clone();
+        let lhs = unwrap_or_else!(notation::parse(lhs.trim(), &leader).ok(), {
+            self.cmd_log(format!("Invalid keys: {lhs}"));
+            return;
+        });
+        let modes: Vec<_> = modes
+            .split(',')
+            .filter(|x| {
+                let key = (x.to_string(), lhs.clone());
+                self.settings.borrow_mut().keymaps.remove(&key).is_some()
+            })
+            .collect();
+        if modes.is_empty() {
+            self.cmd_log(format!("No such mapping: {}", notation::pretty(&lhs)));
+            return;
+        }
+        self.persist_keymap(&modes, &lhs, None);
+    }
+
+    /// The first of the comma separated `modes` that can't have keymaps.
+    fn unknown_mode<'a>(&self, modes: &'a str) -> Option<&'a str> {
+        let keymaps = &self.settings.borrow().keymaps;
+        modes.split(',').find(|x| !is_mode(keymaps, x))
+    }
+
+    /// `maps [modes] [keys]` lists the mappings of the modes (all by default)
+    /// starting with the keys.
+    fn list_maps(&mut self, args: &str) {
+        let mut split = args.split_whitespace();
+        let modes: Vec<_> = split.next().map_or(vec![], |x| x.split(',').collect());
+        let leader = self.settings.borrow().opts.leader.clone();
+        let prefix = notation::parse(split.next().unwrap_or(""), &leader).unwrap_or_default();
+        let mut maps: Vec<_> = self
+            .settings
+            .borrow()
+            .keymaps
+            .iter()
+            .filter(|((mode, keys), _)| {
+                (modes.is_empty() || modes.contains(&mode.as_str())) && keys.starts_with(&prefix)
+            })
+            .map(|((mode, keys), action)| format!("{mode} {} {action}", notation::pretty(keys)))
+            .collect();
+        if maps.is_empty() {
+            self.cmd_log("No mappings found".to_string());
+            return;
+        }
+        maps.sort();
+        self.shown = maps;
+    }
+
+    fn check_maps(&mut self) {
+        let timeout = self.settings.borrow().opts.timeoutlen > 0;
+        let problems = check_keymaps(&self.settings.borrow().keymaps, timeout);
+        if problems.is_empty() {
+            self.cmd_log("No keymap problems".to_string());
+        } else {
+            self.shown = problems;
+        }
+    }
+
+    /// `colorscheme` shows the current colorscheme, `colorscheme {name}`
+    /// replaces every color with the scheme's.
+    fn colorscheme(&mut self, args: &str) {
+        if args.is_empty() {
+            let name = self.settings.borrow().colorscheme.clone();
+            self.cmd_log(name);
+            return;
+        }
+        match colorscheme::load(args) {
+            Ok((colors, warnings)) => {
+                let mut settings = self.settings.borrow_mut();
+                settings.colors = colors;
+                settings.colorscheme = args.to_string();
+                drop(settings);
+                if !warnings.is_empty() {
+                    self.cmd_log(warnings.join(" | "));
                 }
-                _ => (),
-            },
-            _ => (),
+            }
+            Err(err_msg) => self.cmd_log(err_msg),
+        }
+    }
+
+    /// `mkconfig[!] [file]` writes the current settings as a config, to the
+    /// config file by default. `!` overwrites an existing file.
+    fn mkconfig(&mut self, args: &str, force: bool) {
+        let file = if args.is_empty() {
+            config::config_file()
+        } else {
+            PathBuf::from(args)
+        };
+        let res = config::write_generated(&self.settings.borrow(), &file, force);
+        match res {
+            Ok(()) => {
+                self.wrote_config = true;
+                self.cmd_log(format!("Wrote {}", file.display()));
+            }
+            Err(err_msg) => self.cmd_log(err_msg),
+        }
+    }
+
+    /// Saves a `map` or `unmap` to the config with `persist-maps` on.
+    fn persist_keymap(&mut self, modes: &[&str], keys: &str, action: Option<&str>) {
+        let settings = self.settings.borrow();
+        if !settings.opts.persist_keymaps {
+            return;
+        }
+        let res = config::save_keymap(modes, keys, action, &settings.opts.leader);
+        drop(settings);
+        match res {
+            Ok(()) => self.wrote_config = true,
+            Err(err_msg) => self.cmd_log(format!("Couldn't save keymaps: {err_msg}")),
+        }
+    }
+
+    /// Feeds keys written in key notation as if they were typed, keymaps and
+    /// all.
+    fn feed_keys(&mut self, args: &str, repeat: Option<u8>) {
+        let leader = self.settings.borrow().opts.leader.clone();
+        let keys = unwrap_or_else!(notation::parse(args, &leader).ok(), {
+            self.cmd_log(format!("Invalid keys: {args}"));
+            return;
+        });
+        if self.replay_depth >= MAX_REPLAY_DEPTH {
+            self.cmd_log("Mapping recursion too deep".to_string());
+            return;
+        }
+        if self.mode == Mode::Command {
+            self.mode = Mode::Normal;
+        }
+        self.flush();
+        self.replay_depth += 1;
+        for _ in 0..repeat.unwrap_or(1) {
+            for key in keys_from_str(&keys) {
+                self.feed(key);
+            }
+        }
+        self.replay_depth -= 1;
+        self.fed_keys = true;
+    }
+
+    fn record(&mut self, args: &str) {
+        if self.recording.is_some() {
+            self.stop_recording();
+            return;
+        }
+        let mut chars = args.chars();
+        match (chars.next(), chars.next()) {
+            (None, _) => self.pending = Some(Pending::Record),
+            (Some(name), None) => self.start_recording(name),
+            _ => self.cmd_log("Invalid usage: record [register]".to_string()),
+        }
+    }
+
+    fn start_recording(&mut self, name: char) {
+        if !Registers::is_valid_name(name) {
+            self.cmd_log(format!("Invalid register: {name}"));
+            return;
+        }
+        self.recording = Some((name, vec![]));
+    }
+
+    fn stop_recording(&mut self) {
+        let (name, mut keys) = unwrap_or_else!(self.recording.take(), {
+            return;
+        });
+        // drop the keys that stopped the recording
+        if self.mode == Mode::Command {
+            if let Some(idx) = keys.iter().rposition(|k| *k == Key::Char(':')) {
+                keys.truncate(idx);
+            }
+        } else {
+            let len = keys.len().saturating_sub(self.curr_keybind.chars().count());
+            keys.truncate(len);
+        }
+        self.registers.borrow_mut().set(name, Register::Keys(keys));
+    }
+
+    fn play(&mut self, args: &str, repeat: Option<u8>) {
+        let count = repeat.unwrap_or(1);
+        let mut chars = args.chars();
+        match (chars.next(), chars.next()) {
+            (None, _) => self.pending = Some(Pending::Play(count)),
+            (Some(name), None) => self.play_register(name, count),
+            _ => self.cmd_log("Invalid usage: [count]play [register]".to_string()),
+        }
+    }
+
+    fn play_register(&mut self, name: char, count: u8) {
+        let last_played = self.registers.borrow().last_played;
+        let name = if name == '@' {
+            unwrap_or_else!(last_played, {
+                self.cmd_log("No previously played register".to_string());
+                return;
+            })
+        } else {
+            name
+        };
+        let register = self.registers.borrow().get(name).cloned();
+        let keys = match register {
+            Some(Register::Keys(keys)) => keys,
+            Some(Register::Cells(_)) => {
+                self.cmd_log(format!("Register {name} doesn't contain a macro"));
+                return;
+            }
+            None => {
+                self.cmd_log(format!("Register {name} is empty"));
+                return;
+            }
+        };
+        if self.replay_depth >= MAX_REPLAY_DEPTH {
+            self.cmd_log("Macro recursion too deep".to_string());
+            return;
+        }
+        self.registers.borrow_mut().last_played = Some(name);
+
+        if self.mode == Mode::Command {
+            self.mode = Mode::Normal;
+        }
+        self.flush();
+        self.replay_depth += 1;
+        for _ in 0..count {
+            for key in &keys {
+                self.feed(*key);
+            }
+        }
+        self.replay_depth -= 1;
+    }
+
+    fn register(&mut self, args: &str) {
+        let mut chars = args.chars();
+        match (chars.next(), chars.next()) {
+            (None, _) => self.pending = Some(Pending::Register),
+            (Some(name), None) => self.select_register(name),
+            _ => self.cmd_log("Invalid usage: register [register]".to_string()),
+        }
+    }
+
+    fn select_register(&mut self, name: char) {
+        if Registers::is_valid_name(name) {
+            self.register = Some(name);
+        } else {
+            self.cmd_log(format!("Invalid register: {name}"));
+        }
+    }
+
+    /// The register named by a leading `"x` in `args`, or else the one
+    /// selected with `"`, and the rest of `args`.
+    fn take_register<'a>(&mut self, args: &'a str) -> Option<(char, &'a str)> {
+        let selected = self.register.take();
+        let Some(rest) = args.strip_prefix('"') else {
+            return Some((selected.unwrap_or(UNNAMED), args));
+        };
+        let mut chars = rest.chars();
+        let name = unwrap_or_else!(chars.next(), {
+            self.cmd_log("Invalid usage: yank/put [\"register]".to_string());
+            return None;
+        });
+        if !Registers::is_valid_name(name) {
+            self.cmd_log(format!("Invalid register: {name}"));
+            return None;
+        }
+        Some((name, chars.as_str().trim_start()))
+    }
+
+    fn yank(&mut self, args: &str) {
+        let (name, target) = unwrap_or_else!(self.take_register(args), {
+            return;
+        });
+        let cells = unwrap_or_else!(self.target_cells(target), {
+            return;
+        });
+        let cells = cells
+            .into_iter()
+            .map(|(y, x)| {
+                let offset = (y as i8 - self.row as i8, x as i8 - self.col as i8);
+                (offset, self.board[(y, x)])
+            })
+            .collect();
+        self.registers
+            .borrow_mut()
+            .set(name, Register::Cells(cells));
+    }
+
+    /// Returns whether the register could be put.
+    fn put(&mut self, args: &str) -> bool {
+        let (name, rest) = unwrap_or_else!(self.take_register(args), {
+            return false;
+        });
+        if !rest.is_empty() {
+            self.cmd_log("Invalid usage: put [\"register]".to_string());
+            return false;
+        }
+        let register = self.registers.borrow().get(name).cloned();
+        let cells = match register {
+            Some(Register::Cells(cells)) => cells,
+            Some(Register::Keys(_)) => {
+                self.cmd_log(format!("Register {name} doesn't contain cells"));
+                return false;
+            }
+            None => {
+                self.cmd_log(format!("Register {name} is empty"));
+                return false;
+            }
+        };
+        let mut changes = vec![];
+        for ((dy, dx), after) in cells {
+            let y = self.row as i8 + dy;
+            let x = self.col as i8 + dx;
+            if !(0..9).contains(&y) || !(0..9).contains(&x) {
+                continue;
+            }
+            let pos = (y as u8, x as u8);
+            let before = self.board[pos];
+            if before == after {
+                continue;
+            }
+            // numbers and notes are put the way they're typed, so numbers
+            // are checked and notes don't replace numbers
+            if is_note(after) {
+                self.note_at(pos, after, &mut changes);
+                continue;
+            }
+            self.wrong.remove(&pos);
+            if after == 0 {
+                self.board[pos] = 0;
+                changes.push(Change { pos, before, after });
+            } else {
+                self.insert_at(pos, after as u8, &mut changes);
+            }
+        }
+        if !changes.is_empty() {
+            self.history.record_change(changes);
+        }
+        true
+    }
+
+    /// Returns whether the target was valid.
+    fn clear(&mut self, args: &str) -> bool {
+        let cells = unwrap_or_else!(self.target_cells(args), {
+            return false;
+        });
+        let mut changes = vec![];
+        for pos in cells {
+            let before = self.board[pos];
+            if before == 0 {
+                continue;
+            }
+            self.board[pos] = 0;
+            self.wrong.remove(&pos);
+            changes.push(Change {
+                pos,
+                before,
+                after: 0,
+            });
+        }
+        if !changes.is_empty() {
+            self.history.record_change(changes);
+        }
+        true
+    }
+
+    /// Clears the target and inserts `repeat`, or enters insert mode for the
+    /// number without it. Returns whether the target was cleared.
+    fn change(&mut self, args: &str, repeat: Option<u8>) -> bool {
+        if repeat.is_some_and(|num| !(1..=9).contains(&num)) {
+            self.cmd_log("Invalid usage: [num]change [target]".to_string());
+            return false;
+        }
+        if !self.clear(args) {
+            return false;
+        }
+        match repeat {
+            Some(num) => {
+                self.insert(Some(num));
+            }
+            None => {
+                self.mode = Mode::Insert;
+                self.change_target = Some(args.to_string());
+            }
+        }
+        true
+    }
+
+    fn operator(&mut self, args: &str, repeat: Option<u8>) {
+        let op = unwrap_or_else!(Operator::parse(args), {
+            self.cmd_log(format!("Invalid operator: {args}"));
+            return;
+        });
+        self.operator = Some((op, repeat));
+        self.mode = Mode::Custom("operator".to_string());
+    }
+
+    fn object(&mut self, args: &str, repeat: Option<u8>) {
+        let (op, count) = unwrap_or_else!(self.operator.take(), {
+            self.cmd_log("Invalid usage: object only follows an operator".to_string());
+            return;
+        });
+        self.run_operator(op, count, repeat, args, false);
+    }
+
+    /// Runs `op` as the equivalent command. The count typed after `t` is the
+    /// note to toggle; for every other operator it multiplies the motion count.
+    fn run_operator(
+        &mut self,
+        op: Operator,
+        count: Option<u8>,
+        repeat: Option<u8>,
+        target: &str,
+        is_motion: bool,
+    ) {
+        self.mode = Mode::Normal;
+        let (note, count) = match op {
+            Operator::Note => (repeat, count),
+            _ => (None, multiply(count, repeat)),
+        };
+        let target = match count {
+            Some(count) if is_motion => format!("{target} {count}"),
+            _ => target.to_string(),
+        };
+        match (op, note) {
+            (Operator::Note, None) => {
+                self.cmd_log("Invalid usage: t<note><motion>".to_string());
+            }
+            (Operator::Note, Some(note)) => self.process_cmd(&format!("{note}note {target}")),
+            _ => self.process_cmd(&format!("{op} {target}")),
+        }
+    }
+
+    /// Cells named by a command's target: a text object (cell, row, col,
+    /// box), a motion with an optional count (next-box 2), or the cursor and
+    /// selection when empty.
+    fn target_cells(&mut self, target: &str) -> Option<Vec<(u8, u8)>> {
+        let pos = (self.row, self.col);
+        if target.is_empty() {
+            return Some(self.targets());
+        }
+        if let Some(cells) = motion::object(target, pos) {
+            return Some(cells);
         }
+        let (name, count) = match target.split_once(' ') {
+            Some((name, count)) => (name, Some(count.trim())),
+            None => (target, None),
+        };
+        let count = match count.map(str::parse) {
+            None => None,
+            Some(Ok(count)) => Some(count),
+            Some(Err(_)) => {
+                self.cmd_log(format!("Invalid target: {target}"));
+                return None;
+            }
+        };
+        let motion = unwrap_or_else!(Motion::parse(name), {
+            self.cmd_log(format!("Invalid target: {target}"));
+            return None;
+        });
+        let wrap = self.settings.borrow().opts.wrap_around;
+        let to = motion.target(&self.board, pos, count, wrap).unwrap_or(pos);
+        Some(path(pos, to))
+    }
+
+    /// Re-applies the last change at the cursor. A count replaces the one the
+    /// change was made with, like in vim.
+    fn repeat_change(&mut self, repeat: Option<u8>) {
+        let (cmd, last_repeat) = unwrap_or_else!(self.last_change.clone(), {
+            self.cmd_log("No previous change".to_string());
+            return;
+        });
+        let repeat = repeat.or(last_repeat).unwrap_or(0);
+        self.process_cmd(&format!("{repeat}{cmd}"));
+    }
+
+    /// The main cursor followed by every other one.
+    fn cursor_positions(&self) -> Vec<(u8, u8)> {
+        let mut positions = vec![(self.row, self.col)];
+        positions.extend(&self.cursors);
+        positions
+    }
+
+    /// Cells a change applies to: every cursor and every selected cell.
+    fn targets(&self) -> Vec<(u8, u8)> {
+        let mut targets = self.cursor_positions();
+        for pos in self.selected.cells() {
+            if !targets.contains(&pos) {
+                targets.push(pos);
+            }
+        }
+        targets
     }
 
     fn undo(&mut self) {
         if let Some(changes) = self.history.undo() {
-            for change in changes {
+            // a cell can change more than once in a single step
+            for change in changes.into_iter().rev() {
                 self.board[change.pos] = change.before;
                 self.wrong.remove(&change.pos);
             }
@@ -983,10 +2041,96 @@ pub fn draw_outlines(s: &Settings, dimensions: &Rect, side: f32) {
     );
 }
 
-pub fn draw_notes(s: &Settings, box_size: f32, x: f32, y: f32, num: u16, font: &Font) {
+/// Outlines a cell, leaving out the sides and corners shared with `neighbors`
+/// (the 8 surrounding cells, row by row).
+fn draw_cell_outline(
+    x: f32,
+    y: f32,
+    square_size: f32,
+    highlight_size: f32,
+    neighbors: [bool; 8],
+    color: Color,
+) {
+    let mut check_corners = [true; 4];
+
+    // SIDES
+    if !neighbors[1] {
+        draw_rectangle(x, y, square_size, highlight_size, color);
+        check_corners[0] = false;
+        check_corners[1] = false;
+    }
+    if !neighbors[3] {
+        draw_rectangle(x, y, highlight_size, square_size, color);
+        check_corners[0] = false;
+        check_corners[3] = false;
+    }
+    if !neighbors[4] {
+        draw_rectangle(
+            x + square_size - highlight_size,
+            y,
+            highlight_size,
+            square_size,
+            color,
+        );
+        check_corners[1] = false;
+        check_corners[2] = false;
+    }
+    if !neighbors[6] {
+        draw_rectangle(
+            x,
+            y + square_size - highlight_size,
+            square_size,
+            highlight_size,
+            color,
+        );
+        check_corners[2] = false;
+        check_corners[3] = false;
+    }
+
+    if check_corners[0] && !neighbors[0] {
+        draw_rectangle(x, y, highlight_size, highlight_size, color);
+    }
+    if check_corners[1] && !neighbors[2] {
+        draw_rectangle(
+            x + square_size - highlight_size,
+            y,
+            highlight_size,
+            highlight_size,
+            color,
+        );
+    }
+    if check_corners[2] && !neighbors[7] {
+        draw_rectangle(
+            x + square_size - highlight_size,
+            y + square_size - highlight_size,
+            highlight_size,
+            highlight_size,
+            color,
+        );
+    }
+    if check_corners[3] && !neighbors[5] {
+        draw_rectangle(
+            x,
+            y + square_size - highlight_size,
+            highlight_size,
+            highlight_size,
+            color,
+        );
+    }
+}
+
+pub fn draw_notes(
+    s: &Settings,
+    font_size: u16,
+    box_size: f32,
+    x: f32,
+    y: f32,
+    num: u16,
+    font: &Font,
+) {
     let text_params = TextParams {
         font: Some(font),
-        font_size: s.get_note_font_size(box_size),
+        font_size,
         font_scale: FONT_SCALE,
         color: s.colors.note_font,
         ..Default::default()
@@ -1012,6 +2156,101 @@ pub fn draw_notes(s: &Settings, box_size: f32, x: f32, y: f32, num: u16, font: &
     }
 }
 
+/// Every command `process_cmd` knows, for completion.
+const COMMANDS: &[&str] = &[
+    "insert",
+    "note",
+    "go",
+    "move",
+    "extend",
+    "search",
+    "nohlsearch",
+    "visual",
+    "select",
+    "cursor",
+    "bookmark",
+    "jump",
+    "mode",
+    "mark",
+    "fill",
+    "import",
+    "highlight",
+    "set",
+    "colorscheme",
+    "map",
+    "noremap",
+    "unmap",
+    "maps",
+    "feed",
+    "checkmaps",
+    "messages",
+    "mkconfig",
+    "undo",
+    "redo",
+    "record",
+    "play",
+    "clear",
+    "change",
+    "operator",
+    "object",
+    "register",
+    "yank",
+    "put",
+    "repeat",
+];
+
+/// Other names `process_cmd` takes besides `COMMANDS`.
+const ALIASES: &[&str] = &[
+    "i",
+    "n",
+    "g",
+    "mov",
+    "noh",
+    "se",
+    "no",
+    "mes",
+    "mkconfig!",
+    "colo",
+];
+
+/// Whether `name` is a sudoku or frame command.
+pub fn is_command(name: &str) -> bool {
+    [COMMANDS, ALIASES, frame::COMMANDS, frame::ALIASES]
+        .iter()
+        .any(|x| x.contains(&name))
+}
+
+/// Completion candidates for the command line typed so far.
+fn completions(line: &str) -> Vec<String> {
+    let line = line.trim_start_matches(|c: char| c.is_ascii_digit());
+    let candidates = match line.split_once(' ') {
+        None => [COMMANDS, frame::COMMANDS].concat(),
+        Some((cmd @ ("set" | "se" | "setlocal" | "setl"), args)) => {
+            let local = cmd.starts_with("setl");
+            let arg = args.rsplit(' ').next().unwrap_or_default();
+            return match arg.split_once('=') {
+                Some((name, _)) => options::find(name).map_or(vec![], |x| x.values()),
+                None => options::OPTIONS
+                    .iter()
+                    .filter(|x| !local || x.scope != Scope::Global)
+                    .map(|x| x.name.to_string())
+                    .chain((!local).then(|| "all".to_string()))
+                    .collect(),
+            };
+        }
+        Some(("colorscheme" | "colo", _)) => return colorscheme::names(),
+        Some(_) => vec![],
+    };
+    candidates.iter().map(|x| x.to_string()).collect()
+}
+
+fn multiply(a: Option<u8>, b: Option<u8>) -> Option<u8> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.saturating_mul(b)),
+        (a, b) => a.or(b),
+    }
+}
+
 fn is_note(num: u16) -> bool {
     num & (1 << NOTE_FLAG) != 0
 }
@@ -1027,3 +2266,176 @@ fn n_bit_off(num: &mut u16, bit: impl Into<u16>) {
 fn n_bit_on(num: &mut u16, bit: impl Into<u16>) {
     *num |= 1 << bit.into();
 }
+
+#[cfg(test)]
+fn test_sudoku() -> Sudoku {
+    let settings = Rc::new(RefCell::new(Settings::from_config(&None).0));
+    Sudoku::new(settings, Rc::default(), Rc::default(), Rc::default())
+}
+
+#[test]
+fn put_checks_numbers() {
+    let mut sudoku = test_sudoku();
+    sudoku.execute("11go");
+    sudoku.execute("5insert");
+    sudoku.execute("yank");
+    sudoku.execute("move right");
+    sudoku.execute("put");
+    assert_eq!(sudoku.board[(0u8, 1u8)], 5);
+    assert!(sudoku.wrong.contains(&(0u8, 1u8)));
+
+    sudoku.execute("move down");
+    sudoku.execute("3note");
+    sudoku.execute("yank");
+    sudoku.execute("move up");
+    sudoku.execute("put");
+    assert_eq!(sudoku.board[(0u8, 1u8)], 5);
+    sudoku.execute("move right");
+    sudoku.execute("put");
+    assert_eq!(sudoku.board[(0u8, 2u8)], sudoku.board[(1u8, 1u8)]);
+    assert!(is_note(sudoku.board[(0u8, 2u8)]));
+}
+
+#[test]
+fn map_checks_modes() {
+    let mut sudoku = test_sudoku();
+    let bind = |mode: &str| (mode.to_string(), "Q".to_string());
+    sudoku.execute("map n Q gg");
+    sudoku.execute("map normal Q gg");
+    sudoku.execute("noremap normal x mode pilgrim");
+    sudoku.execute("noremap pilgrim Q undo");
+    let keymaps = &sudoku.settings.borrow().keymaps;
+    assert!(!keymaps.contains_key(&bind("n")));
+    assert_eq!(keymaps[&bind("normal")], "feed gg");
+    assert_eq!(keymaps[&bind("pilgrim")], "undo");
+}
+
+#[test]
+fn operators_take_targets() {
+    let mut sudoku = test_sudoku();
+    let keymaps = sudoku.settings.borrow().keymaps.clone();
+    let bind = |keys: &str| keymaps[&("normal".to_string(), keys.to_string())].as_str();
+    assert_eq!((bind("n"), bind("t")), ("note", "operator note"));
+    sudoku.execute("operator note");
+    sudoku.execute("5object box");
+    assert_eq!(sudoku.mode, Mode::Normal);
+    for (y, x) in motion::object("box", (4, 4)).unwrap() {
+        assert!(is_note(sudoku.board[(y, x)]));
+        assert_ne!(sudoku.board[(y, x)] & (1 << 4), 0);
+    }
+    sudoku.execute("7insert");
+    sudoku.execute("operator clear");
+    sudoku.execute("object row");
+    assert!((0..9).all(|x| sudoku.board[(4u8, x)] == 0));
+    assert!(is_note(sudoku.board[(3u8, 4u8)]));
+}
+
+#[test]
+fn yank_takes_quoted_registers() {
+    let mut sudoku = test_sudoku();
+    sudoku.execute("4insert");
+    sudoku.execute("move right");
+    sudoku.execute("yank l");
+    sudoku.execute("yank \"r row");
+    let registers = sudoku.registers.borrow();
+    assert!(matches!(registers.get(UNNAMED), Some(Register::Cells(x)) if x.len() == 2));
+    assert!(matches!(registers.get('r'), Some(Register::Cells(x)) if x.len() == 9));
+    assert!(registers.get('l').is_none());
+}
+
+#[test]
+fn search_keeps_normal_mode() {
+    let mut sudoku = test_sudoku();
+    sudoku.execute("11go");
+    sudoku.execute("5insert");
+    sudoku.execute("99go");
+    sudoku.execute("5insert");
+    sudoku.execute("search 5");
+    assert_eq!(sudoku.mode, Mode::Normal);
+    assert_eq!((sudoku.row, sudoku.col), (0, 0));
+    sudoku.curr_keybind = "n".to_string();
+    assert!(sudoku.try_keybind());
+    assert_eq!((sudoku.row, sudoku.col), (8, 8));
+    sudoku.execute("undo");
+    assert_eq!(sudoku.board[(8u8, 8u8)], 0);
+    sudoku.execute("nohlsearch");
+    sudoku.curr_keybind = "n".to_string();
+    assert!(sudoku.try_keybind());
+    assert_eq!(sudoku.mode, Mode::Note);
+}
+
+#[test]
+fn repeat_redoes_the_last_change() {
+    let mut sudoku = test_sudoku();
+    sudoku.execute("5insert");
+    sudoku.execute("move right");
+    sudoku.execute("repeat");
+    assert_eq!(sudoku.board[(4u8, 5u8)], 5);
+    sudoku.execute("move right");
+    sudoku.execute("3repeat");
+    assert_eq!(sudoku.board[(4u8, 6u8)], 3);
+    sudoku.execute("move down");
+    sudoku.execute("2note");
+    sudoku.execute("move left");
+    sudoku.execute("repeat");
+    assert_eq!(sudoku.board[(5u8, 5u8)], sudoku.board[(5u8, 6u8)]);
+    assert!(is_note(sudoku.board[(5u8, 5u8)]));
+}
+
+#[test]
+fn cursors_change_together() {
+    let mut sudoku = test_sudoku();
+    sudoku.execute("cursor add right");
+    sudoku.execute("cursor add right");
+    assert_eq!((sudoku.row, sudoku.col), (4, 6));
+    sudoku.execute("7insert");
+    assert!((4..7).all(|x| sudoku.board[(4u8, x)] == 7));
+    sudoku.execute("undo");
+    assert!((4..7).all(|x| sudoku.board[(4u8, x)] == 0));
+    sudoku.execute("move down");
+    sudoku.execute("1insert");
+    assert!((4..7).all(|x| sudoku.board[(5u8, x)] == 1));
+    sudoku.execute("cursor clear");
+    sudoku.execute("move down");
+    sudoku.execute("2insert");
+    assert_eq!(sudoku.board[(6u8, 6u8)], 2);
+    assert!((4..6).all(|x| sudoku.board[(6u8, x)] == 0));
+}
+
+#[test]
+fn maps_lists_one_per_line() {
+    let mut sudoku = test_sudoku();
+    sudoku.execute("maps normal C");
+    assert!(sudoku.shown.len() > 1);
+    assert!(sudoku.shown.iter().all(|x| x.starts_with("normal C")));
+    sudoku.execute("noremap normal Q banana");
+    sudoku.execute("checkmaps");
+    assert_eq!(sudoku.shown, ["normal Q: unknown command \"banana\""]);
+}
+
+#[test]
+fn set_all_shows_one_per_line() {
+    let mut sudoku = test_sudoku();
+    sudoku.execute("set all");
+    assert_eq!(sudoku.shown.len(), options::OPTIONS.len());
+    sudoku.shown.clear();
+    sudoku.execute("set wrap?");
+    assert!(sudoku.shown.is_empty());
+    assert_eq!(sudoku.cmd.text, "wrap=false");
+}
+
+#[test]
+fn repeat_keeps_only_working_changes() {
+    let mut sudoku = test_sudoku();
+    sudoku.execute("5insert");
+    sudoku.execute("12insert");
+    sudoku.execute("move right");
+    sudoku.execute("repeat");
+    assert_eq!(sudoku.board[(4u8, 5u8)], 5);
+    sudoku.execute("change cell");
+    sudoku.execute("3insert");
+    sudoku.execute("move left");
+    sudoku.execute("repeat");
+    assert_eq!(sudoku.board[(4u8, 4u8)], 3);
+    assert_eq!(sudoku.last_change, Some(("change cell".to_string(), Some(3))));
+}
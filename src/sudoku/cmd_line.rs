@@ -0,0 +1,206 @@
+/// The command line: its text, the cursor in it, and where it is in the
+/// history and tab completion.
+#[derive(Default)]
+pub struct CmdLine {
+    pub text: String,
+    /// Byte offset of the cursor in `text`.
+    pub cursor: usize,
+    history_idx: Option<usize>,
+    /// What was typed before browsing the history, used as a prefix filter.
+    typed: String,
+    completion: Option<Completion>,
+}
+
+struct Completion {
+    start: usize,
+    candidates: Vec<String>,
+    idx: usize,
+}
+
+impl CmdLine {
+    /// Replaces the text, putting the cursor at the end.
+    pub fn set(&mut self, text: String) {
+        *self = Self::default();
+        self.set_text(text);
+    }
+
+    fn set_text(&mut self, text: String) {
+        self.text = text;
+        self.cursor = self.text.len();
+    }
+
+    pub fn insert(&mut self, str: &str) {
+        self.text.insert_str(self.cursor, str);
+        self.cursor += str.len();
+    }
+
+    pub fn backspace(&mut self) {
+        if let Some(c) = self.text[..self.cursor].chars().next_back() {
+            self.cursor -= c.len_utf8();
+            self.text.remove(self.cursor);
+        }
+    }
+
+    /// Deletes back to and including the previous word separator.
+    pub fn delete_word(&mut self) {
+        while let Some(c) = self.text[..self.cursor].chars().next_back() {
+            self.backspace();
+            if let ' ' | '_' | '\'' = c {
+                break;
+            }
+        }
+    }
+
+    pub fn delete(&mut self) {
+        if self.cursor < self.text.len() {
+            self.text.remove(self.cursor);
+        }
+    }
+
+    pub fn left(&mut self) {
+        if let Some(c) = self.text[..self.cursor].chars().next_back() {
+            self.cursor -= c.len_utf8();
+        }
+    }
+
+    pub fn right(&mut self) {
+        if let Some(c) = self.text[self.cursor..].chars().next() {
+            self.cursor += c.len_utf8();
+        }
+    }
+
+    pub fn home(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn end(&mut self) {
+        self.cursor = self.text.len();
+    }
+
+    /// Goes to the previous history entry starting with what was typed.
+    pub fn history_prev(&mut self, entries: &[String]) {
+        let idx = match self.history_idx {
+            Some(idx) => idx,
+            None => {
+                self.typed = self.text.clone();
+                entries.len()
+            }
+        };
+        if let Some(idx) = entries[..idx]
+            .iter()
+            .rposition(|x| x.starts_with(&self.typed))
+        {
+            self.history_idx = Some(idx);
+            self.set_text(entries[idx].clone());
+        }
+    }
+
+    /// Goes to the next history entry, or back to what was typed.
+    pub fn history_next(&mut self, entries: &[String]) {
+        let Some(idx) = self.history_idx else {
+            return;
+        };
+        let next = entries[idx + 1..]
+            .iter()
+            .position(|x| x.starts_with(&self.typed));
+        match next {
+            Some(offset) => {
+                self.history_idx = Some(idx + 1 + offset);
+                self.set_text(entries[idx + 1 + offset].clone());
+            }
+            None => {
+                self.history_idx = None;
+                self.set_text(self.typed.clone());
+            }
+        }
+    }
+
+    /// Replaces the word before the cursor with the next (or previous)
+    /// candidate. `candidates` gets the text before the cursor.
+    pub fn complete(&mut self, candidates: impl FnOnce(&str) -> Vec<String>, backwards: bool) {
+        if let Some(completion) = &mut self.completion {
+            let len = completion.candidates.len();
+            completion.idx = if backwards {
+                (completion.idx + len - 1) % len
+            } else {
+                (completion.idx + 1) % len
+            };
+        } else {
+            let before = &self.text[..self.cursor];
+            let start = match before.rfind([' ', '=']) {
+                Some(idx) => idx + 1,
+                None => {
+                    before.len()
+                        - before
+                            .trim_start_matches(|c: char| c.is_ascii_digit())
+                            .len()
+                }
+            };
+            let word = &before[start..];
+            let candidates: Vec<_> = candidates(before)
+                .into_iter()
+                .filter(|x| x.starts_with(word))
+                .collect();
+            if candidates.is_empty() {
+                return;
+            }
+            let idx = if backwards { candidates.len() - 1 } else { 0 };
+            self.completion = Some(Completion {
+                start,
+                candidates,
+                idx,
+            });
+        }
+        let Some(completion) = &self.completion else {
+            return;
+        };
+        let candidate = &completion.candidates[completion.idx];
+        self.text
+            .replace_range(completion.start..self.cursor, candidate);
+        self.cursor = completion.start + candidate.len();
+    }
+
+    pub fn end_completion(&mut self) {
+        self.completion = None;
+    }
+
+    /// The candidates being cycled through and the current one.
+    pub fn completions(&self) -> Option<(&[String], usize)> {
+        self.completion
+            .as_ref()
+            .map(|x| (x.candidates.as_slice(), x.idx))
+    }
+}
+
+#[test]
+fn cmd_line_works() {
+    let mut cmd = CmdLine::default();
+    cmd.insert("set wrp");
+    cmd.left();
+    cmd.insert("a");
+    cmd.end();
+    assert_eq!(cmd.text, "set wrap");
+    cmd.delete_word();
+    assert_eq!(cmd.text, "set");
+    cmd.home();
+    cmd.delete();
+    assert_eq!((cmd.text.as_str(), cmd.cursor), ("et", 0));
+
+    let entries = ["set wrap=true", "undo", "set check-input=false"].map(String::from);
+    cmd.set("set".to_string());
+    cmd.history_prev(&entries);
+    assert_eq!(cmd.text, "set check-input=false");
+    cmd.history_prev(&entries);
+    assert_eq!(cmd.text, "set wrap=true");
+    cmd.history_next(&entries);
+    cmd.history_next(&entries);
+    assert_eq!(cmd.text, "set");
+
+    let candidates = |_: &str| ["undo", "unmark", "redo"].map(String::from).to_vec();
+    cmd.set("3un".to_string());
+    cmd.complete(candidates, false);
+    assert_eq!(cmd.text, "3undo");
+    cmd.complete(candidates, false);
+    assert_eq!(cmd.text, "3unmark");
+    assert_eq!(cmd.completions().map(|x| x.1), Some(1));
+}
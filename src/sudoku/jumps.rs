@@ -0,0 +1,48 @@
+/// Cursor positions left by large moves, navigable like vim's jump list.
+#[derive(Default)]
+pub struct JumpList {
+    entries: Vec<(u8, u8)>,
+    idx: usize,
+}
+
+impl JumpList {
+    pub fn push(&mut self, pos: (u8, u8)) {
+        self.entries.retain(|x| *x != pos);
+        self.entries.push(pos);
+        self.idx = self.entries.len();
+    }
+
+    pub fn older(&mut self, curr: (u8, u8)) -> Option<(u8, u8)> {
+        if self.idx == 0 {
+            return None;
+        }
+        if self.idx == self.entries.len() {
+            // remember where we came from so `newer` can get back to it
+            self.push(curr);
+            self.idx -= 1;
+        }
+        self.idx -= 1;
+        Some(self.entries[self.idx])
+    }
+
+    pub fn newer(&mut self) -> Option<(u8, u8)> {
+        if self.idx + 1 >= self.entries.len() {
+            return None;
+        }
+        self.idx += 1;
+        Some(self.entries[self.idx])
+    }
+}
+
+#[test]
+fn jump_list_works() {
+    let mut jumps = JumpList::default();
+    jumps.push((0, 0));
+    jumps.push((1, 1));
+    assert_eq!(jumps.older((2, 2)), Some((1, 1)));
+    assert_eq!(jumps.older((1, 1)), Some((0, 0)));
+    assert_eq!(jumps.older((0, 0)), None);
+    assert_eq!(jumps.newer(), Some((1, 1)));
+    assert_eq!(jumps.newer(), Some((2, 2)));
+    assert_eq!(jumps.newer(), None);
+}
@@ -8,19 +8,159 @@ pub enum Mode {
     Insert,
     Note,
     Go(u8),
+    Visual(VisualKind),
     Custom(String),
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VisualKind {
+    /// Every cell the cursor passes over, never unmarked on backtrack.
+    Path,
+    /// The rectangle between the anchor and the cursor.
+    Block,
+    Row,
+    Col,
+    Box,
+}
+
+impl VisualKind {
+    pub fn parse(str: &str) -> Option<Self> {
+        Some(match str {
+            "" | "path" => Self::Path,
+            "block" => Self::Block,
+            "row" => Self::Row,
+            "col" => Self::Col,
+            "box" => Self::Box,
+            _ => return None,
+        })
+    }
+
+    /// Cells selected between `anchor` and `cursor`. Path selections are
+    /// built up move by move instead.
+    pub fn cells(self, anchor: (u8, u8), cursor: (u8, u8)) -> Vec<(u8, u8)> {
+        let rows = anchor.0.min(cursor.0)..=anchor.0.max(cursor.0);
+        let cols = anchor.1.min(cursor.1)..=anchor.1.max(cursor.1);
+        let (rows, cols) = match self {
+            Self::Path => return vec![],
+            Self::Block => (rows, cols),
+            Self::Row => (rows, 0..=8),
+            Self::Col => (0..=8, cols),
+            Self::Box => (
+                (rows.start() / 3) * 3..=(rows.end() / 3) * 3 + 2,
+                (cols.start() / 3) * 3..=(cols.end() / 3) * 3 + 2,
+            ),
+        };
+        rows.flat_map(|y| cols.clone().map(move |x| (y, x)))
+            .collect()
+    }
+}
+
+/// A command waiting for a register name as its argument.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pending {
+    Record,
+    Play(u8),
+    Register,
+    Bookmark,
+    Jump,
+    /// Ctrl-R on the command line: insert a register's contents.
+    CmdRegister,
+}
+
+/// An operator waiting for a motion or text object to act on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Clear,
+    Change,
+    Yank,
+    Note,
+}
+
+impl Operator {
+    pub fn parse(str: &str) -> Option<Self> {
+        Some(match str {
+            "clear" => Self::Clear,
+            "change" => Self::Change,
+            "yank" => Self::Yank,
+            "note" => Self::Note,
+            _ => return None,
+        })
+    }
+}
+
+impl Display for Operator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", format!("{self:?}").to_lowercase())
+    }
+}
+
+/// Modes keymaps can be bound in besides custom ones.
+pub const NAMES: &[&str] = &[
+    "normal",
+    "note",
+    "insert",
+    "go",
+    "highlight",
+    "visual",
+    "visual-block",
+    "visual-row",
+    "visual-col",
+    "visual-box",
+    "search",
+    "operator",
+];
+
+impl Mode {
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "normal" => Self::Normal,
+            "highlight" => Self::Highlight,
+            "insert" => Self::Insert,
+            "note" => Self::Note,
+            "go" => Self::Go(0),
+            "visual" => Self::Visual(VisualKind::Path),
+            _ => match name.strip_prefix("visual-").and_then(VisualKind::parse) {
+                Some(kind) => Self::Visual(kind),
+                None => Self::Custom(name.to_string()),
+            },
+        }
+    }
+}
+
 impl Display for Mode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Mode::Go(..) => write!(f, "go"),
+            Mode::Visual(VisualKind::Path) => write!(f, "visual"),
+            Mode::Visual(kind) => write!(f, "visual-{}", format!("{kind:?}").to_lowercase()),
             Mode::Custom(s) => write!(f, "{s}"),
             _ => write!(f, "{}", format!("{self:?}").to_lowercase()),
         }
     }
 }
 
+#[test]
+fn mode_from_name_works() {
+    assert_eq!(Mode::from_name("insert"), Mode::Insert);
+    assert_eq!(Mode::from_name("go"), Mode::Go(0));
+    assert_eq!(Mode::from_name("visual"), Mode::Visual(VisualKind::Path));
+    assert_eq!(Mode::from_name("visual-box"), Mode::Visual(VisualKind::Box));
+    assert_eq!(
+        Mode::from_name("visual-banana"),
+        Mode::Custom("visual-banana".to_string())
+    );
+}
+
+#[test]
+fn visual_cells_work() {
+    assert_eq!(
+        VisualKind::Block.cells((1, 2), (0, 1)),
+        vec![(0, 1), (0, 2), (1, 1), (1, 2)]
+    );
+    assert_eq!(VisualKind::Row.cells((4, 4), (4, 0)).len(), 9);
+    assert_eq!(VisualKind::Box.cells((0, 0), (4, 1)).len(), 18);
+}
+
 #[test]
 fn display_mode_works() {
     assert_eq!("command".to_string(), Mode::Command.to_string());
@@ -29,4 +169,8 @@ fn display_mode_works() {
         "banana".to_string(),
         Mode::Custom("banana".to_string()).to_string()
     );
+    assert_eq!(
+        "visual-block".to_string(),
+        Mode::Visual(VisualKind::Block).to_string()
+    );
 }
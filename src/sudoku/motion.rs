@@ -0,0 +1,243 @@
+use crate::sudoku::{is_note, selection::Selection, sudoku_board::SudokuBoard};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Motion {
+    Up,
+    Down,
+    Left,
+    Right,
+    NextEmpty,
+    PrevEmpty,
+    /// Next cell with exactly `count` candidates (2 by default).
+    NextCandidates,
+    PrevCandidates,
+    /// Box `count`, keeping the position inside the box.
+    Box,
+    NextBox,
+    PrevBox,
+    RowStart,
+    RowEnd,
+    ColStart,
+    /// Last row, or row `count` if one is given.
+    ColEnd,
+}
+
+impl Motion {
+    pub fn parse(str: &str) -> Option<Self> {
+        Some(match str {
+            "u" | "up" => Self::Up,
+            "d" | "down" => Self::Down,
+            "l" | "left" => Self::Left,
+            "r" | "right" => Self::Right,
+            "next-empty" => Self::NextEmpty,
+            "prev-empty" => Self::PrevEmpty,
+            "next-candidates" => Self::NextCandidates,
+            "prev-candidates" => Self::PrevCandidates,
+            "box" => Self::Box,
+            "next-box" => Self::NextBox,
+            "prev-box" => Self::PrevBox,
+            "row-start" => Self::RowStart,
+            "row-end" => Self::RowEnd,
+            "col-start" => Self::ColStart,
+            "col-end" => Self::ColEnd,
+            _ => return None,
+        })
+    }
+
+    /// Motions that can move the cursor far enough to be worth remembering.
+    pub fn is_jump(self) -> bool {
+        !matches!(self, Self::Up | Self::Down | Self::Left | Self::Right)
+    }
+
+    /// Where the motion takes the cursor at `pos`, or `None` if it can't move.
+    pub fn target(
+        self,
+        board: &SudokuBoard,
+        pos: (u8, u8),
+        count: Option<u8>,
+        wrap: bool,
+    ) -> Option<(u8, u8)> {
+        let (y, x) = pos;
+        let times = count.unwrap_or(1);
+        match self {
+            Self::Up => Some((step(y, -1, times, wrap), x)),
+            Self::Down => Some((step(y, 1, times, wrap), x)),
+            Self::Left => Some((y, step(x, -1, times, wrap))),
+            Self::Right => Some((y, step(x, 1, times, wrap))),
+            Self::NextEmpty | Self::PrevEmpty => {
+                let forward = self == Self::NextEmpty;
+                let mut pos = pos;
+                for _ in 0..times {
+                    pos = scan(pos, forward, wrap, |p| Selection::is_empty_cell(board[p]))?;
+                }
+                Some(pos)
+            }
+            Self::NextCandidates | Self::PrevCandidates => {
+                let wanted = count.unwrap_or(2) as u32;
+                let forward = self == Self::NextCandidates;
+                scan(pos, forward, wrap, |p| {
+                    is_note(board[p]) && (board[p] & 0x1ff).count_ones() == wanted
+                })
+            }
+            Self::Box => {
+                let k = count?;
+                if !(1..=9).contains(&k) {
+                    return None;
+                }
+                let k = k - 1;
+                Some(((k / 3) * 3 + y % 3, (k % 3) * 3 + x % 3))
+            }
+            Self::NextBox | Self::PrevBox => {
+                let mut b = (y / 3) * 3 + x / 3;
+                let start = ((b / 3) * 3, (b % 3) * 3);
+                let mut times = times;
+                if self == Self::PrevBox && pos != start {
+                    // like vim's `b`, first go to the start of the current box
+                    times -= 1;
+                }
+                for _ in 0..times {
+                    b = match (self, b) {
+                        (Self::NextBox, 8) | (Self::PrevBox, 0) if !wrap => break,
+                        (Self::NextBox, _) => (b + 1) % 9,
+                        _ => (b + 8) % 9,
+                    };
+                }
+                Some(((b / 3) * 3, (b % 3) * 3))
+            }
+            Self::RowStart => Some((y, 0)),
+            Self::RowEnd => Some((y, 8)),
+            Self::ColStart => Some((0, x)),
+            Self::ColEnd => match count {
+                Some(row @ 1..=9) => Some((row - 1, x)),
+                Some(_) => None,
+                None => Some((8, x)),
+            },
+        }
+    }
+}
+
+/// Cells passed going from `from` to `to`, both included: the straight line
+/// between them if they share a row or column, the cells between them in
+/// reading order otherwise.
+pub fn path(from: (u8, u8), to: (u8, u8)) -> Vec<(u8, u8)> {
+    let mut passed = vec![];
+    if from.0 == to.0 || from.1 == to.1 {
+        for y in from.0.min(to.0)..=from.0.max(to.0) {
+            for x in from.1.min(to.1)..=from.1.max(to.1) {
+                passed.push((y, x));
+            }
+        }
+    } else {
+        let (start, end) = (from.0 * 9 + from.1, to.0 * 9 + to.1);
+        for idx in start.min(end)..=start.max(end) {
+            passed.push((idx / 9, idx % 9));
+        }
+    }
+    passed
+}
+
+/// Cells of the text object `name` around `pos`.
+pub fn object(name: &str, pos: (u8, u8)) -> Option<Vec<(u8, u8)>> {
+    let (y, x) = pos;
+    Some(match name {
+        "cell" => vec![pos],
+        "row" => (0..9).map(|x| (y, x)).collect(),
+        "col" => (0..9).map(|y| (y, x)).collect(),
+        "box" => {
+            let (by, bx) = ((y / 3) * 3, (x / 3) * 3);
+            (0..9).map(|i| (by + i / 3, bx + i % 3)).collect()
+        }
+        _ => return None,
+    })
+}
+
+fn step(n: u8, dir: i8, times: u8, wrap: bool) -> u8 {
+    let moved = n as i16 + dir as i16 * times as i16;
+    if wrap {
+        moved.rem_euclid(9) as u8
+    } else {
+        moved.clamp(0, 8) as u8
+    }
+}
+
+/// Next cell in reading order from `pos` (exclusive) matching `pred`.
+pub fn scan(
+    pos: (u8, u8),
+    forward: bool,
+    wrap: bool,
+    pred: impl Fn((u8, u8)) -> bool,
+) -> Option<(u8, u8)> {
+    let start = pos.0 as i16 * 9 + pos.1 as i16;
+    for i in 1..=81 {
+        let idx = if forward { start + i } else { start - i };
+        if !wrap && !(0..81).contains(&idx) {
+            return None;
+        }
+        let idx = idx.rem_euclid(81) as u8;
+        let p = (idx / 9, idx % 9);
+        if pred(p) {
+            return Some(p);
+        }
+    }
+    None
+}
+
+#[test]
+fn path_works() {
+    assert_eq!(path((2, 3), (2, 1)), vec![(2, 1), (2, 2), (2, 3)]);
+    assert_eq!(path((0, 8), (1, 1)), vec![(0, 8), (1, 0), (1, 1)]);
+    assert_eq!(object("box", (4, 5)).unwrap()[8], (5, 5));
+}
+
+#[test]
+fn motions_work() {
+    let mut board = SudokuBoard([[1; 9]; 9]);
+    board[(4u8, 2u8)] = 0;
+    board[(8u8, 8u8)] = 0b1000000000000101;
+
+    assert_eq!(
+        Motion::Left.target(&board, (0, 1), Some(3), false),
+        Some((0, 0))
+    );
+    assert_eq!(
+        Motion::Left.target(&board, (0, 1), Some(3), true),
+        Some((0, 7))
+    );
+    assert_eq!(
+        Motion::NextEmpty.target(&board, (0, 0), None, false),
+        Some((4, 2))
+    );
+    assert_eq!(Motion::NextEmpty.target(&board, (8, 8), None, false), None);
+    assert_eq!(
+        Motion::NextEmpty.target(&board, (8, 8), None, true),
+        Some((4, 2))
+    );
+    assert_eq!(
+        Motion::PrevCandidates.target(&board, (8, 8), None, true),
+        Some((8, 8))
+    );
+    assert_eq!(
+        Motion::Box.target(&board, (1, 2), Some(9), false),
+        Some((7, 8))
+    );
+    assert_eq!(
+        Motion::NextBox.target(&board, (1, 2), Some(2), false),
+        Some((0, 6))
+    );
+    assert_eq!(
+        Motion::PrevBox.target(&board, (4, 4), None, false),
+        Some((3, 3))
+    );
+    assert_eq!(
+        Motion::PrevBox.target(&board, (3, 3), None, false),
+        Some((3, 0))
+    );
+    assert_eq!(
+        Motion::NextBox.target(&board, (8, 8), None, false),
+        Some((6, 6))
+    );
+    assert_eq!(
+        Motion::ColEnd.target(&board, (0, 4), Some(3), false),
+        Some((2, 4))
+    );
+}
@@ -0,0 +1,70 @@
+use crate::sudoku::is_note;
+
+/// What a search looks for in a cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Query {
+    /// `5`: 5 is placed or is a candidate.
+    Digit(u8),
+    /// `=5`: 5 is placed.
+    Placed(u8),
+    /// `{3,8}`: 3 and 8 are both candidates.
+    Candidates(u16),
+    /// `#2`: exactly 2 candidates.
+    Count(u32),
+}
+
+impl Query {
+    pub fn parse(str: &str) -> Option<Self> {
+        let str = str.trim();
+        if let Some(digit) = str.strip_prefix('=') {
+            return parse_digit(digit).map(Self::Placed);
+        }
+        if let Some(count) = str.strip_prefix('#') {
+            return match count.trim().parse() {
+                Ok(n @ 0..=9) => Some(Self::Count(n)),
+                _ => None,
+            };
+        }
+        if let Some(set) = str.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+            let mut mask = 0;
+            for digit in set.split(',') {
+                mask |= 1 << (parse_digit(digit)? - 1);
+            }
+            return Some(Self::Candidates(mask));
+        }
+        parse_digit(str).map(Self::Digit)
+    }
+
+    pub fn matches(&self, cell: u16) -> bool {
+        let candidates = if is_note(cell) { cell & 0x1ff } else { 0 };
+        match *self {
+            Self::Digit(n) => cell == n as u16 || candidates & (1 << (n - 1)) > 0,
+            Self::Placed(n) => cell == n as u16,
+            Self::Candidates(mask) => candidates & mask == mask,
+            Self::Count(n) => is_note(cell) && candidates.count_ones() == n,
+        }
+    }
+}
+
+fn parse_digit(str: &str) -> Option<u8> {
+    match str.trim().parse() {
+        Ok(n @ 1..=9) => Some(n),
+        _ => None,
+    }
+}
+
+#[test]
+fn query_works() {
+    let note = 0b1000000010000100; // candidates 3 and 8
+    assert_eq!(Query::parse("{3, 8}"), Some(Query::Candidates(0b10000100)));
+    assert_eq!(Query::parse("{3,0}"), None);
+    assert_eq!(Query::parse("0"), None);
+
+    assert!(Query::parse("3").unwrap().matches(note));
+    assert!(Query::parse("3").unwrap().matches(3));
+    assert!(!Query::parse("=3").unwrap().matches(note));
+    assert!(Query::parse("{3,8}").unwrap().matches(note));
+    assert!(!Query::parse("{3,7}").unwrap().matches(note));
+    assert!(Query::parse("#2").unwrap().matches(note));
+    assert!(!Query::parse("#2").unwrap().matches(0));
+}
@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+
+use crate::sudoku::{is_note, query::Query, sudoku_board::SudokuBoard};
+
+/// A set of cells, one bitmask per row.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Selection([u16; 9]);
+
+impl Selection {
+    pub fn from_fn(mut f: impl FnMut(u8, u8) -> bool) -> Self {
+        let mut selection = Self::default();
+        for y in 0..9u8 {
+            for x in 0..9u8 {
+                if f(y, x) {
+                    selection.set(y, x);
+                }
+            }
+        }
+        selection
+    }
+
+    pub fn toggle(&mut self, y: impl Into<usize>, x: impl Into<usize>) {
+        self.0[y.into()] ^= 1 << x.into();
+    }
+
+    pub fn set(&mut self, y: impl Into<usize>, x: impl Into<usize>) {
+        self.0[y.into()] |= 1 << x.into();
+    }
+
+    pub fn get(&self, y: impl Into<usize>, x: impl Into<usize>) -> bool {
+        self.0[y.into()] & (1 << x.into()) > 0
+    }
+
+    pub fn clear(&mut self) {
+        for x in &mut self.0 {
+            *x = 0;
+        }
+    }
+
+    pub fn cells(&self) -> impl Iterator<Item = (u8, u8)> + '_ {
+        (0..9u8).flat_map(move |y| {
+            (0..9u8)
+                .filter(move |x| self.get(y, *x))
+                .map(move |x| (y, x))
+        })
+    }
+
+    pub fn len(&self) -> u32 {
+        self.0.iter().map(|row| row.count_ones()).sum()
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        Self(std::array::from_fn(|i| self.0[i] | other.0[i]))
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        Self(std::array::from_fn(|i| self.0[i] & other.0[i]))
+    }
+
+    pub fn difference(&self, other: &Self) -> Self {
+        Self(std::array::from_fn(|i| self.0[i] & !other.0[i]))
+    }
+
+    pub fn complement(&self) -> Self {
+        Self(std::array::from_fn(|i| !self.0[i] & 0x1ff))
+    }
+
+    /// Whether a cell counts as empty, notes and all.
+    pub fn is_empty_cell(cell: u16) -> bool {
+        cell == 0 || is_note(cell)
+    }
+
+    /// Cells matching every space separated term, e.g. `empty box 5`.
+    ///
+    /// Terms are `all`, `empty`, `filled`, `bivalue`, `peers`, `row`/`col`/`box`
+    /// (of the cursor, or the given 1-9 one), `@{name}` for a saved selection,
+    /// or a search query. A leading `!` negates a term.
+    pub fn matching(
+        terms: &str,
+        board: &SudokuBoard,
+        cursor: (u8, u8),
+        saved: &HashMap<char, Selection>,
+    ) -> Result<Self, String> {
+        let (cy, cx) = cursor;
+        let mut result = Self::default().complement();
+        let mut terms = terms.split_whitespace().peekable();
+        while let Some(term) = terms.next() {
+            let (negate, term) = match term.strip_prefix('!') {
+                Some(term) => (true, term),
+                None => (false, term),
+            };
+            let unit = matches!(term, "row" | "col" | "box");
+            let index = match terms.peek().map(|n| n.parse::<u8>()) {
+                Some(Ok(n @ 1..=9)) if unit => {
+                    terms.next();
+                    n - 1
+                }
+                _ => match term {
+                    "row" => cy,
+                    "col" => cx,
+                    _ => cy / 3 * 3 + cx / 3,
+                },
+            };
+            let set = match term {
+                "all" => Self::default().complement(),
+                "empty" => Self::from_fn(|y, x| Self::is_empty_cell(board[(y, x)])),
+                "filled" => Self::from_fn(|y, x| !Self::is_empty_cell(board[(y, x)])),
+                "bivalue" => Self::from_fn(|y, x| Query::Count(2).matches(board[(y, x)])),
+                "peers" => Self::from_fn(|y, x| {
+                    (y, x) != cursor && (y == cy || x == cx || (y / 3, x / 3) == (cy / 3, cx / 3))
+                }),
+                "row" => Self::from_fn(|y, _| y == index),
+                "col" => Self::from_fn(|_, x| x == index),
+                "box" => Self::from_fn(|y, x| y / 3 * 3 + x / 3 == index),
+                _ if term.starts_with('@') => {
+                    let mut chars = term.chars().skip(1);
+                    match (chars.next(), chars.next()) {
+                        (Some(name), None) => *saved
+                            .get(&name)
+                            .ok_or(format!("No saved selection: {name}"))?,
+                        _ => return Err(format!("Invalid saved selection: {term}")),
+                    }
+                }
+                _ => {
+                    let query = Query::parse(term).ok_or(format!("Invalid predicate: {term}"))?;
+                    Self::from_fn(|y, x| query.matches(board[(y, x)]))
+                }
+            };
+            let set = if negate { set.complement() } else { set };
+            result = result.intersection(&set);
+        }
+        Ok(result)
+    }
+}
+
+#[test]
+fn selection_algebra_works() {
+    let row = Selection::from_fn(|y, _| y == 0);
+    let col = Selection::from_fn(|_, x| x == 0);
+    assert_eq!(row.union(&col).len(), 17);
+    assert_eq!(row.intersection(&col).len(), 1);
+    assert_eq!(row.difference(&col).len(), 8);
+    assert_eq!(row.complement().len(), 72);
+
+    let mut board = SudokuBoard([[0; 9]; 9]);
+    board[(0u8, 0u8)] = 5;
+    board[(4u8, 4u8)] = 0b1000000000001010; // candidates 2 and 4
+    let saved = HashMap::from([('a', row)]);
+    let select = |terms| Selection::matching(terms, &board, (0, 0), &saved);
+
+    assert_eq!(select("empty box 1").unwrap().len(), 8);
+    assert_eq!(select("peers").unwrap().len(), 20);
+    assert_eq!(
+        select("bivalue").unwrap(),
+        Selection::from_fn(|y, x| (y, x) == (4, 4))
+    );
+    assert_eq!(select("4 !box 1").unwrap().len(), 1);
+    assert_eq!(select("@a !filled").unwrap().len(), 8);
+    assert!(select("@b").is_err());
+    assert!(select("bogus").is_err());
+}
@@ -1,4 +1,7 @@
-use std::{fmt::Display, ops::{Deref, DerefMut, Index, IndexMut}};
+use std::{
+    fmt::Display,
+    ops::{Deref, DerefMut, Index, IndexMut},
+};
 
 use crate::sudoku::{ALL_NOTES, history::Change, is_note, n_bit_off};
 
@@ -24,7 +27,7 @@ pub enum BacktrackResult {
 }
 
 impl SudokuBoard {
-    pub fn from_str(&mut self, str: &str) -> Option<SudokuBoard> {
+    pub fn parse(str: &str) -> Option<SudokuBoard> {
         let mut new = SudokuBoard([[0; 9]; 9]);
         for (i, x) in str.chars().enumerate() {
             if !x.is_ascii_digit() {
@@ -36,7 +39,7 @@ impl SudokuBoard {
     }
 
     pub fn fill_cell_candidates(&mut self, changes: &mut Vec<Change>) {
-        let bbefore = self.clone();
+        let bbefore = *self;
 
         for row in &mut self.0.iter_mut() {
             for col in row.iter_mut() {
@@ -108,7 +111,7 @@ impl SudokuBoard {
     }
 
     pub fn solve(&mut self) -> BacktrackResult {
-        return self.backtrack(&mut BacktrackResult::NoSolution);
+        self.backtrack(&mut BacktrackResult::NoSolution)
     }
 
     fn backtrack(&mut self, solve_state: &mut BacktrackResult) -> BacktrackResult {
@@ -127,13 +130,13 @@ impl SudokuBoard {
                 }
             }
             self[(y, x)] = before;
-            return *solve_state;
+            *solve_state
         } else {
-            return if let BacktrackResult::OneSolution(..) = *solve_state {
+            if let BacktrackResult::OneSolution(..) = *solve_state {
                 BacktrackResult::MoreSolutions
             } else {
-                BacktrackResult::OneSolution(self.clone())
-            };
+                BacktrackResult::OneSolution(*self)
+            }
         }
     }
 